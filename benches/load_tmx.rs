@@ -0,0 +1,49 @@
+//! TMX load times with and without the WebP cache. Run with `cargo bench --bench load_tmx`.
+//!
+//! `uncached` is what every load cost before the cache existed, `disk_cache` is a fresh process
+//! loading a map whose images were encoded by an earlier run, and `memory_cache` is reloading a
+//! map (or loading another one sharing its tilesets) in the same process.
+
+#![feature(test)]
+
+extern crate test;
+
+use pixel_arena_game::tiled::load_tmx;
+use pixel_arena_game::tiled::source::Source;
+use pixel_arena_game::tiled::tileset::cache;
+
+use test::Bencher;
+
+const MAP: &str = "assets/maps/placeholder/simple-grass-test.tmx";
+
+fn cache_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join("pixel-arena-game-bench")
+}
+
+#[bench]
+fn uncached(b: &mut Bencher) {
+    cache::set_dir(None);
+    b.iter(|| {
+        cache::clear_memory();
+        load_tmx(Source::new_file(MAP)).unwrap().data
+    });
+}
+
+#[bench]
+fn disk_cache(b: &mut Bencher) {
+    cache::set_dir(Some(cache_dir()));
+    cache::clear_memory();
+    load_tmx(Source::new_file(MAP)).unwrap();
+    b.iter(|| {
+        cache::clear_memory();
+        load_tmx(Source::new_file(MAP)).unwrap().data
+    });
+}
+
+#[bench]
+fn memory_cache(b: &mut Bencher) {
+    cache::set_dir(None);
+    cache::clear_memory();
+    load_tmx(Source::new_file(MAP)).unwrap();
+    b.iter(|| load_tmx(Source::new_file(MAP)).unwrap().data);
+}
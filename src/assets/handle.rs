@@ -0,0 +1,67 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// A reference to an asset in an `AssetManager`. The asset stays loaded for as long as any
+/// clone of its handle is alive.
+pub struct Handle<T> {
+    slot: Arc<Slot>,
+    // fn() -> T so handles are Send and Sync whatever T is, they never hold a T
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Shared by every handle to an asset. The manager holds a weak reference to tell when the
+/// last handle is gone.
+pub(super) struct Slot {
+    index: usize,
+}
+
+impl Slot {
+    pub(super) fn new(index: usize) -> Arc<Slot> {
+        Arc::new(Slot { index })
+    }
+}
+
+impl<T> Handle<T> {
+    pub(super) fn new(slot: Arc<Slot>) -> Handle<T> {
+        Handle {
+            slot,
+            _marker: PhantomData,
+        }
+    }
+
+    pub(super) fn index(&self) -> usize {
+        self.slot.index
+    }
+
+    /// Number of live handles to the asset, including this one
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.slot)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle::new(self.slot.clone())
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.slot, &other.slot)
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (&*self.slot as *const Slot).hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Handle({})", self.slot.index)
+    }
+}
@@ -0,0 +1,171 @@
+use crate::assets::Asset;
+use crate::components::Sprite;
+use crate::graphics::textures::TextureId;
+use crate::graphics::GraphicsState;
+use crate::tiled;
+use crate::tiled::map::gpu::{MapGpu, MapImages};
+use crate::tiled::map::Map;
+use crate::tiled::raw;
+use crate::tiled::source::Source;
+use crate::tiled::tileset::{Tileset, TilesetGpu};
+use crate::tiled::Parsed;
+
+use std::sync::Arc;
+
+use failure::{Fallible, ResultExt};
+use math2d::Vector2f;
+use serde::de::DeserializeOwned;
+
+/// A single-image texture, e.g. for a sprite
+pub struct TextureAsset {
+    pub texture: TextureId,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The contents of any file, e.g. game config
+pub struct DataFile {
+    pub data: Arc<[u8]>,
+}
+
+/// A map along with what it needs on the GPU to be drawn
+pub struct MapAsset {
+    pub map: Map,
+    pub gpu: MapGpu,
+}
+
+/// A standalone tileset along with its textures
+pub struct TilesetAsset {
+    pub tileset: Tileset,
+    pub gpu: TilesetGpu,
+}
+
+/// A map loaded on a worker, with its images already decoded so `upload` only has to create
+/// the textures
+pub struct DecodedMap {
+    map: Map,
+    images: MapImages,
+}
+
+/// A tileset loaded on a worker, with its image already decoded
+pub struct DecodedTileset {
+    tileset: Tileset,
+    image: image::RgbaImage,
+}
+
+impl TextureAsset {
+    pub fn sprite(&self) -> Sprite {
+        Sprite {
+            texture: self.texture,
+            subtexture: Default::default(),
+        }
+    }
+}
+
+impl Asset for TextureAsset {
+    type Decoded = image::RgbaImage;
+
+    fn decode(source: &Source) -> Fallible<Parsed<image::RgbaImage>> {
+        let data = source.read_all()?;
+        let img = image::load_from_memory(&data).context("Decoding image data")?;
+        Ok(Parsed {
+            data: img.to_rgba(),
+            warnings: Vec::new(),
+        })
+    }
+
+    fn upload(rgba: image::RgbaImage, graphics: &mut GraphicsState) -> Fallible<TextureAsset> {
+        let (width, height) = rgba.dimensions();
+        let texture = graphics.textures.insert_rgba(&graphics.core, rgba, false)?;
+        Ok(TextureAsset {
+            texture,
+            width,
+            height,
+        })
+    }
+
+    fn release(self, graphics: &mut GraphicsState) {
+        graphics.textures.free(self.texture);
+    }
+}
+
+impl DataFile {
+    pub fn json<T: DeserializeOwned>(&self) -> Fallible<T> {
+        Ok(serde_json::from_slice(&self.data)?)
+    }
+}
+
+impl Asset for DataFile {
+    type Decoded = Arc<[u8]>;
+
+    fn decode(source: &Source) -> Fallible<Parsed<Arc<[u8]>>> {
+        Ok(Parsed {
+            data: source.read_all()?,
+            warnings: Vec::new(),
+        })
+    }
+
+    fn upload(data: Arc<[u8]>, _: &mut GraphicsState) -> Fallible<DataFile> {
+        Ok(DataFile { data })
+    }
+}
+
+/// Loads TMX, Tiled JSON (`.tmj`), binmaps and JSON maps, by extension. Physics isn't created.
+impl Asset for MapAsset {
+    type Decoded = DecodedMap;
+
+    fn decode(source: &Source) -> Fallible<Parsed<DecodedMap>> {
+        let (map, warnings) = match source.extension() {
+            Some("tmj") => {
+                let parsed = tiled::load_tmj(source.clone())?;
+                (parsed.data, parsed.warnings)
+            }
+            Some("binmap") => (tiled::load_binmap(&source.read_all()?[..])?, Vec::new()),
+            Some("json") => (tiled::load_jsonmap(&source.read_all()?[..])?, Vec::new()),
+            _ => {
+                let parsed = tiled::load_tmx(source.clone())?;
+                (parsed.data, parsed.warnings)
+            }
+        };
+        let images = MapImages::decode(&map)?;
+        Ok(Parsed {
+            data: DecodedMap { map, images },
+            warnings,
+        })
+    }
+
+    fn upload(decoded: DecodedMap, graphics: &mut GraphicsState) -> Fallible<MapAsset> {
+        let DecodedMap { mut map, images } = decoded;
+        let gpu = MapGpu::upload(&mut map, images, &graphics.core)?;
+        Ok(MapAsset { map, gpu })
+    }
+}
+
+/// Loads a standalone TSX or Tiled JSON (`.tsj`) tileset, scaled so one of its tiles is one
+/// world unit
+impl Asset for TilesetAsset {
+    type Decoded = DecodedTileset;
+
+    fn decode(source: &Source) -> Fallible<Parsed<DecodedTileset>> {
+        let raw = match source.extension() {
+            Some("tsj") | Some("json") => {
+                raw::json::JsonContext::parse(source.clone(), raw::json::parse_tileset)?
+            }
+            _ => raw::ParseContext::parse(source.clone(), "tileset", raw::Tileset::parse_tag)?,
+        };
+        let tile_size = Vector2f::new(raw.data.tilewidth as f32, raw.data.tileheight as f32);
+        let tileset = Tileset::from_raw(&raw.data, tile_size)?;
+        tileset.validate()?;
+        let image = tileset.image.to_rgba().context("Decoding tileset image")?;
+        Ok(Parsed {
+            data: DecodedTileset { tileset, image },
+            warnings: raw.warnings,
+        })
+    }
+
+    fn upload(decoded: DecodedTileset, graphics: &mut GraphicsState) -> Fallible<TilesetAsset> {
+        let DecodedTileset { mut tileset, image } = decoded;
+        let gpu = tileset.upload(&graphics.core, image)?;
+        Ok(TilesetAsset { tileset, gpu })
+    }
+}
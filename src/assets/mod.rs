@@ -0,0 +1,295 @@
+//! Asynchronous asset loading. `AssetManager::load` returns a typed `Handle` right away and
+//! queues the asset to be read and decoded on a worker thread. Anything that needs the GPU
+//! (uploading textures, tileset images) happens in `AssetManager::update` on the main thread,
+//! which the `UpdateAssets` system runs every frame. Assets are freed once the last handle to
+//! them is dropped.
+//!
+//! Assets are found through the asset filesystem (`vfs`), and loading the same file as the same
+//! type again while a handle to it is alive shares the existing asset.
+
+use crate::graphics::GraphicsState;
+use crate::tiled::raw::Diagnostic;
+use crate::tiled::source::Source;
+use crate::tiled::Parsed;
+use crate::Data;
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Weak;
+
+use failure::Fallible;
+use index_pool::IndexPool;
+
+pub use self::handle::Handle;
+pub use self::kinds::{DataFile, MapAsset, TextureAsset, TilesetAsset};
+
+pub mod handle;
+pub mod kinds;
+pub mod vfs;
+
+mod worker;
+
+/// Something the asset manager can load
+pub trait Asset: Sized + 'static {
+    /// What a worker thread produces from the file
+    type Decoded: Send + 'static;
+
+    /// Reads and decodes the file, along with any warnings about it. Runs on a worker thread, so
+    /// must not touch the GPU.
+    fn decode(source: &Source) -> Fallible<Parsed<Self::Decoded>>;
+
+    /// Finishes loading on the main thread, e.g. by uploading textures
+    fn upload(decoded: Self::Decoded, graphics: &mut GraphicsState) -> Fallible<Self>;
+
+    /// Frees anything `upload` created outside the asset itself. Runs on the main thread once
+    /// the last handle is dropped.
+    fn release(self, _graphics: &mut GraphicsState) {}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    /// See `AssetManager::error` for why
+    Failed,
+}
+
+pub struct AssetManager {
+    entries: Vec<Option<Entry>>,
+    free: IndexPool,
+    by_source: HashMap<(TypeId, Source), usize>,
+    workers: worker::Pool,
+    /// Tells apart loads that reuse the slot of a dropped asset
+    next_load: u64,
+}
+
+struct Entry {
+    key: (TypeId, Source),
+    handle: Weak<handle::Slot>,
+    load: u64,
+    state: State,
+    warnings: Vec<Diagnostic>,
+    upload: fn(Box<dyn Any + Send>, &mut GraphicsState) -> Fallible<Box<dyn Any>>,
+    release: fn(Box<dyn Any>, &mut GraphicsState),
+}
+
+enum State {
+    Loading,
+    Loaded(Box<dyn Any>),
+    Failed(String),
+}
+
+impl AssetManager {
+    pub fn new() -> Fallible<AssetManager> {
+        Ok(AssetManager {
+            entries: Vec::new(),
+            free: IndexPool::new(),
+            by_source: HashMap::new(),
+            workers: worker::Pool::new()?,
+            next_load: 0,
+        })
+    }
+
+    /// Loads the asset at `path` relative to the assets root, e.g. `maps/arena.tmx`
+    pub fn load<T: Asset>(&mut self, path: &str) -> Handle<T> {
+        self.load_source(Source::asset(path))
+    }
+
+    pub fn load_source<T: Asset>(&mut self, source: Source) -> Handle<T> {
+        let key = (TypeId::of::<T>(), source);
+        if let Some(&index) = self.by_source.get(&key) {
+            let entry = self.entries[index].as_ref().unwrap();
+            if let Some(slot) = entry.handle.upgrade() {
+                return Handle::new(slot);
+            }
+        }
+
+        let index = self.free.new_id();
+        let slot = handle::Slot::new(index);
+        let load = self.next_load;
+        self.next_load += 1;
+
+        let entry = Entry {
+            key: key.clone(),
+            handle: std::sync::Arc::downgrade(&slot),
+            load,
+            state: State::Loading,
+            warnings: Vec::new(),
+            upload: upload_erased::<T>,
+            release: release_erased::<T>,
+        };
+        if index == self.entries.len() {
+            self.entries.push(Some(entry));
+        } else {
+            self.entries[index] = Some(entry);
+        }
+        self.by_source.insert(key.clone(), index);
+        self.workers.queue(worker::Job {
+            index,
+            load,
+            source: key.1,
+            vfs: vfs::current(),
+            decode: decode_erased::<T>,
+        });
+
+        Handle::new(slot)
+    }
+
+    pub fn state<T: Asset>(&self, handle: &Handle<T>) -> LoadState {
+        match &self.entry(handle).state {
+            State::Loading => LoadState::Loading,
+            State::Loaded(_) => LoadState::Loaded,
+            State::Failed(_) => LoadState::Failed,
+        }
+    }
+
+    /// The asset, once it has finished loading
+    pub fn get<T: Asset>(&self, handle: &Handle<T>) -> Option<&T> {
+        match &self.entry(handle).state {
+            State::Loaded(asset) => asset.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut<T: Asset>(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        let entry = self.entries[handle.index()].as_mut().unwrap();
+        match &mut entry.state {
+            State::Loaded(asset) => asset.downcast_mut(),
+            _ => None,
+        }
+    }
+
+    /// Why the asset failed to load
+    pub fn error<T: Asset>(&self, handle: &Handle<T>) -> Option<&str> {
+        match &self.entry(handle).state {
+            State::Failed(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Problems found while decoding the asset that didn't keep it from loading
+    pub fn warnings<T: Asset>(&self, handle: &Handle<T>) -> &[Diagnostic] {
+        &self.entry(handle).warnings
+    }
+
+    /// Number of assets still being loaded
+    pub fn pending(&self) -> usize {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.as_ref())
+            .filter(|entry| entry.handle.upgrade().is_some())
+            .filter(|entry| match entry.state {
+                State::Loading => true,
+                _ => false,
+            })
+            .count()
+    }
+
+    /// Finishes loading whatever the workers have decoded since the last call and frees assets
+    /// that no longer have any handles. Never blocks.
+    pub fn update(&mut self, graphics: &mut GraphicsState) {
+        while let Some(done) = self.workers.try_recv() {
+            self.finish(done, graphics);
+        }
+        self.collect(graphics);
+    }
+
+    /// Like `update`, but waits for `handle` to finish loading first. For loading screens and
+    /// startup, where there's nothing else to do in the meantime.
+    pub fn wait_for<T: Asset>(&mut self, handle: &Handle<T>, graphics: &mut GraphicsState) {
+        while let State::Loading = self.entry(handle).state {
+            match self.workers.recv() {
+                Some(done) => self.finish(done, graphics),
+                None => break,
+            }
+        }
+        self.update(graphics);
+    }
+
+    fn entry<T>(&self, handle: &Handle<T>) -> &Entry {
+        // Entries outlive their handles, so this is always there
+        self.entries[handle.index()].as_ref().unwrap()
+    }
+
+    fn finish(&mut self, done: worker::Done, graphics: &mut GraphicsState) {
+        let entry = match self.entries.get_mut(done.index) {
+            Some(Some(entry)) => entry,
+            _ => return,
+        };
+        if entry.load != done.load {
+            // The asset was dropped while it was loading and its slot reused
+            return;
+        }
+
+        let upload = entry.upload;
+        let warnings = &mut entry.warnings;
+        let result = done.result.and_then(|decoded| {
+            *warnings = decoded.warnings;
+            upload(decoded.data, graphics)
+        });
+        entry.state = match result {
+            Ok(asset) => State::Loaded(asset),
+            Err(err) => {
+                let chain = err
+                    .iter_chain()
+                    .map(|fail| fail.to_string())
+                    .collect::<Vec<_>>();
+                State::Failed(format!("Loading {}: {}", entry.key.1, chain.join(": ")))
+            }
+        };
+    }
+
+    fn collect(&mut self, graphics: &mut GraphicsState) {
+        for index in 0..self.entries.len() {
+            let dead = match &self.entries[index] {
+                Some(entry) => entry.handle.upgrade().is_none(),
+                None => false,
+            };
+            if !dead {
+                continue;
+            }
+
+            let entry = self.entries[index].take().unwrap();
+            if self.by_source.get(&entry.key) == Some(&index) {
+                self.by_source.remove(&entry.key);
+            }
+            let _ = self.free.return_id(index);
+            if let State::Loaded(asset) = entry.state {
+                (entry.release)(asset, graphics);
+            }
+        }
+    }
+}
+
+fn decode_erased<T: Asset>(source: &Source) -> Fallible<Parsed<Box<dyn Any + Send>>> {
+    let decoded = T::decode(source)?;
+    Ok(Parsed {
+        data: Box::new(decoded.data),
+        warnings: decoded.warnings,
+    })
+}
+
+fn upload_erased<T: Asset>(
+    decoded: Box<dyn Any + Send>,
+    graphics: &mut GraphicsState,
+) -> Fallible<Box<dyn Any>> {
+    let decoded = decoded
+        .downcast::<T::Decoded>()
+        .expect("Decoded asset has the type it was queued with");
+    Ok(Box::new(T::upload(*decoded, graphics)?))
+}
+
+fn release_erased<T: Asset>(asset: Box<dyn Any>, graphics: &mut GraphicsState) {
+    if let Ok(asset) = asset.downcast::<T>() {
+        asset.release(graphics);
+    }
+}
+
+#[derive(Default, conniecs::System)]
+#[process = "update_assets"]
+pub struct UpdateAssets;
+
+fn update_assets(_: &mut UpdateAssets, data: &mut Data) {
+    let services = &mut data.services;
+    services.assets.update(&mut services.graphics);
+}
@@ -0,0 +1,294 @@
+//! The asset filesystem. Assets are looked up by their path relative to the assets root (e.g.
+//! `maps/arena.tmx`) in a stack of layers, each a directory or a pack file, and the highest
+//! priority layer that has the file supplies it. That lets mods and DLC replace base assets
+//! without touching the install directory.
+//!
+//! By default the layers are, from lowest to highest priority:
+//!
+//! - `base`: `$ARENA_PACK`, `assets.pack` or the `assets` directory, whichever is found first
+//! - every directory or `.pack` file in `dlc/`, in name order
+//! - every directory or `.pack` file in `mods/`, in name order
+//!
+//! Paths are relative to the working directory. The VFS is per thread; the asset manager's
+//! workers use the VFS of the thread that queued each load.
+
+use crate::tiled::source::pack::Pack;
+use crate::tiled::source::{absolute, normalize, Source};
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible, ResultExt};
+
+thread_local! {
+    static CURRENT: RefCell<Arc<Vfs>> = RefCell::new(Arc::new(Vfs::from_install()));
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Vfs {
+    /// Lowest priority first
+    layers: Vec<Layer>,
+    skipped: Vec<Skipped>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub name: String,
+    pub kind: LayerKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum LayerKind {
+    Dir(PathBuf),
+    Pack(Arc<Pack>),
+}
+
+/// A directory or pack that `Vfs::from_install` or `Vfs::add_all` couldn't add as a layer
+#[derive(Clone, Debug)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Where an asset comes from, as listed by `Vfs::list`
+#[derive(Clone, Debug)]
+pub struct Asset {
+    pub path: String,
+    /// The layer that supplies the asset
+    pub layer: String,
+    /// Lower priority layers that also have the asset, highest priority first
+    pub overrides: Vec<String>,
+}
+
+/// Replaces the VFS used by `Source` on this thread
+pub fn mount(vfs: impl Into<Arc<Vfs>>) {
+    let vfs = vfs.into();
+    CURRENT.with(|current| *current.borrow_mut() = vfs);
+}
+
+pub fn current() -> Arc<Vfs> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+impl Vfs {
+    pub fn new() -> Vfs {
+        Default::default()
+    }
+
+    /// The default layers described in the module docs. Layers that fail to load are skipped
+    /// so a broken mod doesn't keep the game from starting, see `skipped`.
+    pub fn from_install() -> Vfs {
+        let mut vfs = Vfs::new();
+
+        let (base, is_pack) = match std::env::var_os("ARENA_PACK") {
+            Some(pack) => (PathBuf::from(pack), true),
+            None if Path::new("assets.pack").is_file() => (PathBuf::from("assets.pack"), true),
+            None => (PathBuf::from("assets"), false),
+        };
+        let result = if is_pack {
+            vfs.add_pack("base", &base)
+        } else {
+            vfs.add_dir("base", &base)
+        };
+        if let Err(err) = result {
+            vfs.skip(&base, err);
+        }
+
+        for dir in ["dlc", "mods"].iter() {
+            let dir = Path::new(dir);
+            if let Err(err) = vfs.add_all(dir) {
+                vfs.skip(dir, err);
+            }
+        }
+
+        vfs
+    }
+
+    /// Adds a directory as the highest priority layer
+    pub fn add_dir(&mut self, name: &str, dir: &Path) -> Fallible<()> {
+        let dir =
+            dunce::canonicalize(dir).with_context(|_| format!("Opening {}", dir.display()))?;
+        if !dir.is_dir() {
+            return Err(err_msg(format!("{} is not a directory", dir.display())));
+        }
+        self.layers.push(Layer {
+            name: name.into(),
+            kind: LayerKind::Dir(dir),
+        });
+        Ok(())
+    }
+
+    /// Adds a pack file as the highest priority layer
+    pub fn add_pack(&mut self, name: &str, path: &Path) -> Fallible<()> {
+        let pack = Pack::open(path)?;
+        self.layers.push(Layer {
+            name: name.into(),
+            kind: LayerKind::Pack(Arc::new(pack)),
+        });
+        Ok(())
+    }
+
+    /// Adds every directory and `.pack` file in `dir` as layers, in name order. Does nothing if
+    /// `dir` doesn't exist. Entries that fail to load are skipped, see `skipped`.
+    pub fn add_all(&mut self, dir: &Path) -> Fallible<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        let mut paths = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.sort();
+        for path in paths {
+            let name = match path.file_stem() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            let result = if path.is_dir() {
+                self.add_dir(&name, &path)
+            } else if path.extension().map_or(false, |ext| ext == "pack") {
+                self.add_pack(&name, &path)
+            } else {
+                continue;
+            };
+            if let Err(err) = result {
+                self.skip(&path, err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Highest priority first
+    pub fn layers(&self) -> impl Iterator<Item = &Layer> {
+        self.layers.iter().rev()
+    }
+
+    /// Directories and packs that were meant to be layers but failed to load, for the caller to
+    /// report
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.skipped
+    }
+
+    /// The asset at `path`, from the highest priority layer that has it
+    pub fn find(&self, path: &str) -> Option<Source> {
+        let path = asset_path(Path::new(path))?;
+        self.layers().find_map(|layer| layer.source(&path))
+    }
+
+    /// The layer that supplies the asset at `path`
+    pub fn origin(&self, path: &str) -> Option<&Layer> {
+        let path = asset_path(Path::new(path))?;
+        self.layers().find(|layer| layer.source(&path).is_some())
+    }
+
+    /// The asset path of a file inside one of the layers, e.g. `assets/maps/arena.tmx` gives
+    /// `maps/arena.tmx`. Used to look up files referenced by path through the VFS.
+    pub fn asset_path_of(&self, file: &Path) -> Option<String> {
+        let file = absolute(file);
+        self.layers()
+            .filter_map(|layer| file.strip_prefix(layer.root()).ok())
+            .find_map(asset_path)
+    }
+
+    /// Every asset in every layer and which layer supplies it, sorted by path
+    pub fn list(&self) -> Fallible<Vec<Asset>> {
+        let mut assets = BTreeMap::<String, Vec<&str>>::new();
+        for layer in self.layers() {
+            let files = layer
+                .files()
+                .with_context(|_| format!("Listing layer {}", layer.name))?;
+            for file in files {
+                assets.entry(file).or_default().push(&layer.name);
+            }
+        }
+
+        Ok(assets
+            .into_iter()
+            .map(|(path, layers)| Asset {
+                path,
+                layer: layers[0].into(),
+                overrides: layers[1..].iter().map(|&name| name.into()).collect(),
+            })
+            .collect())
+    }
+
+    fn skip(&mut self, path: &Path, err: failure::Error) {
+        let chain = err
+            .iter_chain()
+            .map(|fail| fail.to_string())
+            .collect::<Vec<_>>();
+        self.skipped.push(Skipped {
+            path: path.to_owned(),
+            error: chain.join(": "),
+        });
+    }
+}
+
+impl Layer {
+    fn source(&self, path: &str) -> Option<Source> {
+        match &self.kind {
+            LayerKind::Dir(dir) => {
+                let file = dir.join(path);
+                if file.is_file() {
+                    Some(Source::File(file))
+                } else {
+                    None
+                }
+            }
+            LayerKind::Pack(pack) if pack.contains(path) => {
+                Some(Source::Packed(pack.clone(), path.into()))
+            }
+            LayerKind::Pack(_) => None,
+        }
+    }
+
+    /// The directory the layer's files are (or were packed) from
+    fn root(&self) -> PathBuf {
+        match &self.kind {
+            LayerKind::Dir(dir) => dir.clone(),
+            LayerKind::Pack(pack) => absolute(Path::new(pack.root())),
+        }
+    }
+
+    fn files(&self) -> Fallible<Vec<String>> {
+        match &self.kind {
+            LayerKind::Dir(dir) => {
+                let mut files = Vec::new();
+                list_dir(dir, dir, &mut files)?;
+                Ok(files)
+            }
+            LayerKind::Pack(pack) => Ok(pack.files().map(String::from).collect()),
+        }
+    }
+}
+
+/// Normalizes a relative path into the `/` separated form assets are keyed by. Returns None if
+/// the path is absolute or escapes the assets root.
+pub fn asset_path(path: &Path) -> Option<String> {
+    if path.is_absolute() {
+        return None;
+    }
+    let path = normalize(path);
+    if path.starts_with("..") {
+        return None;
+    }
+    let parts = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    Some(parts.join("/"))
+}
+
+fn list_dir(root: &Path, dir: &Path, files: &mut Vec<String>) -> Fallible<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_dir(root, &path, files)?;
+        } else if let Some(file) = path.strip_prefix(root).ok().and_then(asset_path) {
+            files.push(file);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use crate::assets::vfs::{self, Vfs};
+use crate::tiled::source::Source;
+use crate::tiled::Parsed;
+
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+
+use failure::{err_msg, Fallible};
+
+const WORKERS: usize = 2;
+
+pub struct Job {
+    pub index: usize,
+    pub load: u64,
+    pub source: Source,
+    pub vfs: Arc<Vfs>,
+    pub decode: fn(&Source) -> Fallible<Parsed<Box<dyn Any + Send>>>,
+}
+
+pub struct Done {
+    pub index: usize,
+    pub load: u64,
+    pub result: Fallible<Parsed<Box<dyn Any + Send>>>,
+}
+
+/// Threads decoding assets. They exit once the pool is dropped and they finish their current
+/// job.
+pub struct Pool {
+    jobs: Sender<Job>,
+    done: Receiver<Done>,
+}
+
+impl Pool {
+    pub fn new() -> Fallible<Pool> {
+        let (jobs, job_recv) = channel::<Job>();
+        let (done_send, done) = channel();
+        let job_recv = Arc::new(Mutex::new(job_recv));
+
+        for i in 0..WORKERS {
+            let jobs = job_recv.clone();
+            let done = done_send.clone();
+            std::thread::Builder::new()
+                .name(format!("asset worker {}", i))
+                .spawn(move || run(jobs, done))?;
+        }
+
+        Ok(Pool { jobs, done })
+    }
+
+    pub fn queue(&self, job: Job) {
+        // Workers only stop once the pool is dropped
+        let _ = self.jobs.send(job);
+    }
+
+    pub fn try_recv(&self) -> Option<Done> {
+        match self.done.try_recv() {
+            Ok(done) => Some(done),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        }
+    }
+
+    /// Blocks until a job finishes. Returns None if every worker has died.
+    pub fn recv(&self) -> Option<Done> {
+        self.done.recv().ok()
+    }
+}
+
+fn run(jobs: Arc<Mutex<Receiver<Job>>>, done: Sender<Done>) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(jobs) => match jobs.recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+
+        vfs::mount(job.vfs);
+        let decode = job.decode;
+        let source = &job.source;
+        let result = catch_unwind(AssertUnwindSafe(|| decode(source)))
+            .unwrap_or_else(|_| Err(err_msg("Decoding panicked")));
+
+        let sent = done.send(Done {
+            index: job.index,
+            load: job.load,
+            result,
+        });
+        if sent.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Lists the asset filesystem's layers and which layer supplies each asset, for checking what a
+//! mod or DLC overrides. Run it from the game's working directory.
+
+use pixel_arena_game::assets::vfs::{self, LayerKind};
+
+const USAGE: &str = "\
+usage: arena-assets [--overrides]
+
+Lists every asset and the layer it's loaded from. With --overrides, only lists assets that
+replace one from a lower priority layer.";
+
+fn main() {
+    let mut only_overrides = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--overrides" => only_overrides = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => {
+                eprintln!("error: unknown argument `{}`\n\n{}", arg, USAGE);
+                std::process::exit(64);
+            }
+        }
+    }
+
+    let vfs = vfs::current();
+    println!("layers, highest priority first:");
+    for layer in vfs.layers() {
+        match &layer.kind {
+            LayerKind::Dir(dir) => println!("    {} ({})", layer.name, dir.display()),
+            LayerKind::Pack(pack) => println!("    {} ({})", layer.name, pack.path().display()),
+        }
+    }
+    println!();
+
+    for skipped in vfs.skipped() {
+        eprintln!(
+            "warning: skipped {}: {}",
+            skipped.path.display(),
+            skipped.error
+        );
+    }
+
+    let assets = match vfs.list() {
+        Ok(assets) => assets,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    for asset in assets.iter() {
+        if asset.overrides.is_empty() {
+            if !only_overrides {
+                println!("{}: {}", asset.path, asset.layer);
+            }
+        } else {
+            println!(
+                "{}: {} (overrides {})",
+                asset.path,
+                asset.layer,
+                asset.overrides.join(", ")
+            );
+        }
+    }
+}
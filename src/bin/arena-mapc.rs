@@ -0,0 +1,249 @@
+//! Compiles Tiled maps into prebaked binmaps (or JSON) and reports anything wrong with them.
+//! Never touches the graphics stack, so it can run on a headless build server.
+//!
+//! Exits with 1 if any map failed to load or validate, or 2 if there were only warnings
+//! (unless `--allow-warnings` is passed).
+
+use pixel_arena_game::tiled::map::layer::Layer;
+use pixel_arena_game::tiled::map::Map;
+use pixel_arena_game::tiled::raw;
+use pixel_arena_game::tiled::source::Source;
+use pixel_arena_game::tiled::{load_tmj, load_tmx, save_binmap, save_jsonmap, Parsed};
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use failure::{err_msg, Fallible, ResultExt};
+
+const USAGE: &str = "\
+usage: arena-mapc [options] <input dir> [<output dir>]
+
+Compiles every .tmx and .tmj map under <input dir> into <output dir>, keeping the directory
+layout. Outputs go next to the maps if no output dir is given.
+
+options:
+    --json            write .json maps instead of .binmap
+    --check           only load and validate, don't write anything
+    --allow-warnings  exit with 0 even if there were warnings
+    -h, --help        show this message";
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Output {
+    Binmap,
+    Json,
+    None,
+}
+
+struct Options {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    format: Output,
+    allow_warnings: bool,
+}
+
+#[derive(Default)]
+struct Totals {
+    maps: usize,
+    failed: usize,
+    warnings: usize,
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            std::process::exit(64);
+        }
+    };
+
+    let mut maps = Vec::new();
+    if let Err(err) = find_maps(&options.input, &mut maps) {
+        eprintln!("error: {}", format_error(&err));
+        std::process::exit(1);
+    }
+    maps.sort();
+
+    let mut totals = Totals::default();
+    for path in maps.iter() {
+        totals.maps += 1;
+        match compile(&options, path) {
+            Ok(warnings) => totals.warnings += warnings,
+            Err(err) => {
+                totals.failed += 1;
+                eprintln!("{}", format_error(&err));
+            }
+        }
+    }
+
+    println!(
+        "{} maps, {} failed, {} warnings",
+        totals.maps, totals.failed, totals.warnings
+    );
+    if totals.failed > 0 {
+        std::process::exit(1);
+    } else if totals.warnings > 0 && !options.allow_warnings {
+        std::process::exit(2);
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Fallible<Option<Options>> {
+    let mut format = Output::Binmap;
+    let mut allow_warnings = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => format = Output::Json,
+            "--check" => format = Output::None,
+            "--allow-warnings" => allow_warnings = true,
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with('-') => {
+                return Err(err_msg(format!("unknown option `{}`", arg)));
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    let mut paths = paths.into_iter();
+    let input = paths
+        .next()
+        .ok_or_else(|| err_msg("missing input directory"))?;
+    let output = paths.next();
+    if paths.next().is_some() {
+        return Err(err_msg("too many arguments"));
+    }
+
+    Ok(Some(Options {
+        input,
+        output,
+        format,
+        allow_warnings,
+    }))
+}
+
+fn find_maps(dir: &Path, maps: &mut Vec<PathBuf>) -> Fallible<()> {
+    let entries = std::fs::read_dir(dir).with_context(|_| format!("reading {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_maps(&path, maps)?;
+        } else if is_map(&path) {
+            maps.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_map(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("tmx") | Some("tmj") => true,
+        _ => false,
+    }
+}
+
+/// Loads, validates and writes out one map. Returns the number of warnings.
+fn compile(options: &Options, path: &Path) -> Fallible<usize> {
+    let source = Source::new_file(path);
+    let result = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("tmj") => load_tmj(source),
+        _ => load_tmx(source),
+    };
+    let Parsed {
+        data: map,
+        warnings,
+    } = result.with_context(|_| format!("{}", path.display()))?;
+    for warning in warnings.iter() {
+        eprintln!("{}", warning);
+    }
+
+    map.validate()
+        .with_context(|_| format!("{}: validation failed", path.display()))?;
+
+    let out_path = match options.format {
+        Output::Binmap => Some(output_path(options, path, "binmap")?),
+        Output::Json => Some(output_path(options, path, "json")?),
+        Output::None => None,
+    };
+    if let Some(out_path) = &out_path {
+        let file = std::fs::File::create(out_path)
+            .with_context(|_| format!("creating {}", out_path.display()))?;
+        let writer = std::io::BufWriter::new(file);
+        let result = match options.format {
+            Output::Json => save_jsonmap(writer, &map),
+            _ => save_binmap(writer, &map),
+        };
+        result.with_context(|_| format!("writing {}", out_path.display()))?;
+    }
+
+    print_stats(path, &map, warnings.len());
+    Ok(warnings.len())
+}
+
+fn output_path(options: &Options, path: &Path, ext: &str) -> Fallible<PathBuf> {
+    let out = match &options.output {
+        Some(output) => {
+            let relative = path.strip_prefix(&options.input)?;
+            output.join(relative)
+        }
+        None => path.to_owned(),
+    };
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|_| format!("creating {}", parent.display()))?;
+    }
+    Ok(out.with_extension(ext))
+}
+
+fn print_stats(path: &Path, map: &Map, warnings: usize) {
+    let mut chunks = 0;
+    let mut tiles = 0;
+    let mut colliders = 0;
+    let mut used_tilesets = HashSet::new();
+    for layer in map.layers.iter() {
+        let layer = match layer {
+            Layer::Tile(layer) => layer,
+            _ => continue,
+        };
+
+        chunks += layer.data.chunks.len();
+        for chunk in layer.data.chunks.values() {
+            used_tilesets.extend(chunk.used_tilesets.iter().cloned());
+            for &tile in chunk.data.iter() {
+                if let Some(tile) = map.tilesets.get_tile(tile) {
+                    tiles += 1;
+                    colliders += tile.colliders.len();
+                }
+            }
+        }
+    }
+
+    println!(
+        "{}: {} layers, {} chunks, {} tiles, {}/{} tilesets used, {} colliders, {} warnings",
+        path.display(),
+        map.layers.len(),
+        chunks,
+        tiles,
+        used_tilesets.len(),
+        map.tilesets.iter().count(),
+        colliders,
+        warnings,
+    );
+}
+
+/// The whole cause chain on one line. Parse errors already say which file they're from.
+fn format_error(err: &failure::Error) -> String {
+    if let Some(diag) = err
+        .iter_chain()
+        .find_map(|f| f.downcast_ref::<raw::Diagnostic>())
+    {
+        return diag.to_string();
+    }
+    err.iter_chain()
+        .map(|fail| fail.to_string())
+        .collect::<Vec<_>>()
+        .join(": ")
+}
@@ -0,0 +1,106 @@
+//! Bundles the assets directory into a single pack file for release builds. The game uses
+//! `assets.pack` in its working directory in place of `assets/`, see `assets::vfs`. Packs can
+//! also be dropped into `dlc/` or `mods/`.
+
+use pixel_arena_game::tiled::source::pack::Pack;
+
+use std::path::{Component, Path, PathBuf};
+
+use failure::{err_msg, Fallible, ResultExt};
+
+const USAGE: &str = "\
+usage: arena-pack [<assets dir> [<output file>]]
+
+Packs every file under <assets dir> (default `assets`) into <output file> (default
+`assets.pack`). The assets dir should be given relative to the directory the game runs from.";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    if args.len() > 2 || args.iter().any(|arg| arg.starts_with('-')) {
+        eprintln!("{}", USAGE);
+        std::process::exit(64);
+    }
+
+    let root = args.get(0).map(|s| s.as_str()).unwrap_or("assets");
+    let output = args.get(1).map(|s| s.as_str()).unwrap_or("assets.pack");
+    if let Err(err) = run(Path::new(root), Path::new(output)) {
+        let chain = err
+            .iter_chain()
+            .map(|fail| fail.to_string())
+            .collect::<Vec<_>>();
+        eprintln!("error: {}", chain.join(": "));
+        std::process::exit(1);
+    }
+}
+
+fn run(root: &Path, output: &Path) -> Fallible<()> {
+    let root_name = root_name(root)?;
+    let mut paths = Vec::new();
+    find_files(root, &mut paths)?;
+    paths.sort();
+
+    let mut files = Vec::with_capacity(paths.len());
+    let mut total = 0;
+    for path in paths {
+        let data = std::fs::read(&path).with_context(|_| format!("reading {}", path.display()))?;
+        let name = path
+            .strip_prefix(root)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        total += data.len();
+        files.push((name, data));
+    }
+
+    let file =
+        std::fs::File::create(output).with_context(|_| format!("creating {}", output.display()))?;
+    Pack::write(std::io::BufWriter::new(file), &root_name, &files)
+        .with_context(|_| format!("writing {}", output.display()))?;
+
+    let packed = std::fs::metadata(output)?.len();
+    println!(
+        "packed {} files from {} into {} ({} -> {} bytes)",
+        files.len(),
+        root.display(),
+        output.display(),
+        total,
+        packed
+    );
+    Ok(())
+}
+
+/// The root as `Source::new_file` will see it, relative and without `.` components
+fn root_name(root: &Path) -> Fallible<String> {
+    let mut parts = Vec::new();
+    for component in root.components() {
+        match component {
+            Component::CurDir => (),
+            Component::Normal(part) => parts.push(part.to_string_lossy()),
+            _ => {
+                return Err(err_msg(format!(
+                    "{} must be relative to the game's working directory, without `..`",
+                    root.display()
+                )));
+            }
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> Fallible<()> {
+    let entries = std::fs::read_dir(dir).with_context(|_| format!("reading {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -19,7 +19,11 @@ impl CurrentFrame {
         self.gameplay_frame.as_mut()
     }
 
-    pub fn begin_frame(&mut self, core: &GraphicsCore) -> Result<(), failure::Error> {
+    pub fn begin_frame(
+        &mut self,
+        core: &GraphicsCore,
+        clear_color: math2d::Color,
+    ) -> Result<(), failure::Error> {
         self.gameplay_frame = None;
         self.window_frame = None;
 
@@ -49,7 +53,8 @@ impl CurrentFrame {
                 .context("creating gameplay framebuffer")?
         };
         
-        gameplay_frame.clear_color_srgb_and_depth((0.0, 0.0, 0.0, 1.0), 0.0);
+        let c = clear_color;
+        gameplay_frame.clear_color_srgb_and_depth((c.r, c.g, c.b, c.a), 0.0);
 
         self.window_frame = Some(frame);
         self.gameplay_frame = Some(gameplay_frame);
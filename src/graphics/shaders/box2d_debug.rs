@@ -10,7 +10,9 @@ static VERT_SHADER: &str = include_str!("box2d_debug/box2d_debug.vert");
 static FRAG_SHADER: &str = include_str!("box2d_debug/box2d_debug.frag");
 
 pub fn load(core: &GraphicsCore) -> Fallible<Box2dDebugShader> {
-    let program = glium::Program::from_source(&core.display, VERT_SHADER, FRAG_SHADER, None)?;
+    let vert = super::load_source("box2d_debug/box2d_debug.vert", VERT_SHADER)?;
+    let frag = super::load_source("box2d_debug/box2d_debug.frag", FRAG_SHADER)?;
+    let program = glium::Program::from_source(&core.display, &vert, &frag, None)?;
 
     Ok(Box2dDebugShader { program })
 }
@@ -0,0 +1,18 @@
+use crate::graphics::core::GraphicsCore;
+
+use failure::Fallible;
+
+pub struct ImageLayerShader {
+    pub program: glium::Program,
+}
+
+static VERT_SHADER: &str = include_str!("image_layer/image_layer.vert");
+static FRAG_SHADER: &str = include_str!("image_layer/image_layer.frag");
+
+pub fn load(core: &GraphicsCore) -> Fallible<ImageLayerShader> {
+    let vert = super::load_source("image_layer/image_layer.vert", VERT_SHADER)?;
+    let frag = super::load_source("image_layer/image_layer.frag", FRAG_SHADER)?;
+    let program = glium::Program::from_source(&core.display, &vert, &frag, None)?;
+
+    Ok(ImageLayerShader { program })
+}
@@ -0,0 +1,20 @@
+#version 400
+
+uniform Camera {
+    mat4 u_camera;
+};
+
+uniform vec4 rect;
+uniform vec4 uvrect;
+uniform float layer;
+
+in vec2 a_uv;
+
+out vec2 v_uv;
+
+void main() {
+    vec2 pos = mix(rect.xy, rect.zw, a_uv);
+
+    gl_Position = u_camera * vec4(pos, layer, 1);
+    v_uv = mix(uvrect.xy, uvrect.zw, a_uv);
+}
@@ -1,8 +1,12 @@
+use crate::assets::vfs;
 use crate::graphics::core::GraphicsCore;
 
-use failure::ResultExt;
+use std::borrow::Cow;
+
+use failure::{Fallible, ResultExt};
 
 pub mod box2d_debug;
+pub mod image_layer;
 pub mod shadow;
 pub mod simple_quad;
 pub mod tile_chunk;
@@ -12,6 +16,7 @@ pub struct Shaders {
     pub shadow: shadow::ShadowShader,
     pub tile_chunk: tile_chunk::TileChunkShader,
     pub box2d_debug: box2d_debug::Box2dDebugShader,
+    pub image_layer: image_layer::ImageLayerShader,
 }
 
 impl Shaders {
@@ -20,12 +25,28 @@ impl Shaders {
         let shadow = shadow::load(core).context("loading shadow shader")?;
         let tile_chunk = tile_chunk::load(core).context("loading tile_chunk shader")?;
         let box2d_debug = box2d_debug::load(core).context("loading box2d_debug shader")?;
+        let image_layer = image_layer::load(core).context("loading image_layer shader")?;
 
         Ok(Shaders {
             simple_quad,
             shadow,
             tile_chunk,
             box2d_debug,
+            image_layer,
         })
     }
 }
+
+/// The source of a shader, from `shaders/<name>` in the asset filesystem if a layer has it or
+/// the copy built into the game otherwise
+fn load_source(name: &str, builtin: &'static str) -> Fallible<Cow<'static, str>> {
+    match vfs::current().find(&format!("shaders/{}", name)) {
+        Some(source) => {
+            let data = source.read_all()?;
+            let text = String::from_utf8(data.to_vec())
+                .with_context(|_| format!("{} is not valid utf8", source))?;
+            Ok(Cow::Owned(text))
+        }
+        None => Ok(Cow::Borrowed(builtin)),
+    }
+}
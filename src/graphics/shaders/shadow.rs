@@ -11,7 +11,9 @@ static VERT_SHADER: &str = include_str!("shadow/shadow.vert");
 static FRAG_SHADER: &str = include_str!("shadow/shadow.frag");
 
 pub fn load(core: &GraphicsCore) -> Result<ShadowShader, failure::Error> {
-    let program = glium::Program::from_source(&core.display, VERT_SHADER, FRAG_SHADER, None)?;
+    let vert = super::load_source("shadow/shadow.vert", VERT_SHADER)?;
+    let frag = super::load_source("shadow/shadow.frag", FRAG_SHADER)?;
+    let program = glium::Program::from_source(&core.display, &vert, &frag, None)?;
     let verts = glium::VertexBuffer::immutable(&core.display, &CIRCLE)?;
 
     Ok(ShadowShader { program, verts })
@@ -27,7 +27,9 @@ static VERT_SHADER: &str = include_str!("simple_quad/simple_quad.vert");
 static FRAG_SHADER: &str = include_str!("simple_quad/simple_quad.frag");
 
 pub fn load(core: &GraphicsCore) -> Result<SimpleQuadShader, Error> {
-    let program = glium::Program::from_source(&core.display, VERT_SHADER, FRAG_SHADER, None)?;
+    let vert = super::load_source("simple_quad/simple_quad.vert", VERT_SHADER)?;
+    let frag = super::load_source("simple_quad/simple_quad.frag", FRAG_SHADER)?;
+    let program = glium::Program::from_source(&core.display, &vert, &frag, None)?;
 
     Ok(SimpleQuadShader { program })
 }
@@ -10,7 +10,9 @@ static VERT_SHADER: &str = include_str!("tile_chunk/tile_chunk.vert");
 static FRAG_SHADER: &str = include_str!("tile_chunk/tile_chunk.frag");
 
 pub fn load(core: &GraphicsCore) -> Fallible<TileChunkShader> {
-    let program = glium::Program::from_source(&core.display, VERT_SHADER, FRAG_SHADER, None)?;
+    let vert = super::load_source("tile_chunk/tile_chunk.vert", VERT_SHADER)?;
+    let frag = super::load_source("tile_chunk/tile_chunk.frag", FRAG_SHADER)?;
+    let program = glium::Program::from_source(&core.display, &vert, &frag, None)?;
 
     Ok(TileChunkShader { program })
 }
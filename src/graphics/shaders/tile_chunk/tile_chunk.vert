@@ -4,10 +4,20 @@ uniform Camera {
     mat4 u_camera;
 };
 
-uniform vec2 chunk_pos;
-uniform vec2 tile_size;
+// Tile position of the chunk's top-left tile
+uniform ivec2 chunk_tile;
+uniform vec2 layer_offset;
+uniform vec2 tile_offset;
 uniform float layer;
 
+// 0 = orthogonal, 1 = isometric, 2 = staggered. See `Orientation::shader_uniforms`.
+uniform int orientation;
+// 0 = x, 1 = y
+uniform int stagger_axis;
+// 0 = even, 1 = odd
+uniform int stagger_index;
+
+// Two texels per tile: the uv rect, then the tile size in world units
 uniform sampler1D rect_buffer;
 
 in vec2 a_pos;
@@ -15,15 +25,61 @@ in vec2 a_uv;
 
 in vec2 tile_pos;
 in int tile_id;
+in int tile_flip;
 
 out vec2 v_uv;
 
+// Mirrors `Orientation::tile_to_world`
+vec2 tile_to_world(ivec2 tile) {
+    if (orientation == 1) {
+        return vec2(tile.x - tile.y, -(tile.x + tile.y)) * 0.5;
+    } else if (orientation == 2) {
+        int along = stagger_axis == 0 ? tile.x : tile.y;
+        float shift = (along & 1) == stagger_index ? 0.5 : 0.0;
+        if (stagger_axis == 0) {
+            return vec2(tile.x * 0.5, -tile.y - shift);
+        } else {
+            return vec2(tile.x + shift, -tile.y * 0.5);
+        }
+    }
+    return vec2(tile.x, -tile.y);
+}
+
 void main() {
-    vec4 uvrect = texelFetch(rect_buffer, tile_id, 0);
-    vec2 pos = chunk_pos + (tile_pos * vec2(1, -1) + a_pos * vec2(1.01, 1.01)) * tile_size;
+    vec4 uvrect = texelFetch(rect_buffer, tile_id * 2, 0);
+    vec2 tile_size = texelFetch(rect_buffer, tile_id * 2 + 1, 0).xy;
+    if ((tile_flip & 1) != 0) {
+        tile_size = tile_size.yx;
+    }
+
+    // Tiles are anchored at the bottom-left corner of their cell like in Tiled, so oversized
+    // tiles extend up and to the right
+    vec2 center = tile_to_world(chunk_tile + ivec2(tile_pos));
+    vec2 cell_corner = center - vec2(0.5, 0.5);
+    vec2 quad_pos = (a_pos * vec2(1.01, 1.01) + vec2(0.5, 0.5)) * tile_size;
+    vec2 pos = layer_offset + cell_corner + quad_pos + tile_offset;
+
+    // Diamond tiles overlap their neighbours, so tiles further down the screen go in front.
+    // The bias stays below 1 so it never crosses into the next layer.
+    float depth = layer;
+    if (orientation != 0) {
+        depth += clamp(4096 - center.y, 0, 8191) / 8192;
+    }
+
+    gl_Position = u_camera * vec4(pos, depth, 1);
+    // Undo the flips in reverse order: vertical, horizontal, then diagonal
+    vec2 uv = a_uv;
+    if ((tile_flip & 2) != 0) {
+        uv.y = 1 - uv.y;
+    }
+    if ((tile_flip & 4) != 0) {
+        uv.x = 1 - uv.x;
+    }
+    if ((tile_flip & 1) != 0) {
+        uv = uv.yx;
+    }
 
-    gl_Position = u_camera * vec4(pos, layer, 1);
-    v_uv = mix(uvrect.xy, uvrect.zw, a_uv);
+    v_uv = mix(uvrect.xy, uvrect.zw, uv);
 }
 
 
@@ -8,13 +8,19 @@ pub struct BeginDraw;
 
 fn process(_: &mut BeginDraw, data: &mut Data) {
     let graphics = &mut data.services.graphics;
+    let clear_color = data
+        .services
+        .assets
+        .get(&data.services.map)
+        .and_then(|map| map.background_color)
+        .unwrap_or(math2d::Color::BLACK);
 
     graphics.camera.update_aspect(&graphics.core);
     graphics.camera.upload();
 
     graphics
         .frame
-        .begin_frame(&graphics.core)
+        .begin_frame(&graphics.core, clear_color)
         .context("BeginDraw::process")
         .unwrap();
 }
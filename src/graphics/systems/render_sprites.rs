@@ -1,5 +1,7 @@
+use crate::assets::MapAsset;
 use crate::graphics::shaders::simple_quad::QuadInstance;
 use crate::graphics::textures::{SubtextureId, TextureId};
+use crate::graphics::GraphicsState;
 use crate::{Data, EntityIter};
 
 use std::collections::HashMap;
@@ -20,7 +22,6 @@ pub struct RenderSprites {
 }
 
 fn process(r: &mut RenderSprites, entities: EntityIter, data: &mut Data) {
-    let map = &mut data.services.map;
     let graphics = &mut data.services.graphics;
     let def_tid = TextureId::default();
     let def_sub = SubtextureId::default();
@@ -29,34 +30,9 @@ fn process(r: &mut RenderSprites, entities: EntityIter, data: &mut Data) {
     let mut num_draws = 0;
     let dt = data.services.time.delta;
 
-    // Draw terrain chunks
-    let viewport = graphics.camera.world_viewport();
-    for (layernum, layer) in map.layers.iter_mut().enumerate() {
-        use crate::tiled::map::layer::Layer;
-        use crate::tiled::map::tiledata::TileData;
-        use crate::tiled::map::tiledata::CHUNK_SIZE;
-        use math2d::Point2f;
-        use math2d::RectCorner::{BottomRight, TopLeft};
-
-        if let Layer::Tile(layer) = layer {
-            let topleft = TileData::chunk_pos(layer.data.tile_pos_at(viewport.corner(TopLeft)));
-            let botright =
-                TileData::chunk_pos(layer.data.tile_pos_at(viewport.corner(BottomRight)));
-            for y in botright.y..=topleft.y {
-                for x in topleft.x..=botright.x {
-                    if let Some(chunk) = layer.data.chunks.get_mut(&(x, y).into()) {
-                        chunk
-                            .initialize(&graphics.core)
-                            .expect("Chunks should be able to initialize their buffers");
-
-                        let pos = Point2f::new((x * CHUNK_SIZE) as f32, (-y * CHUNK_SIZE) as f32);
-                        chunk
-                            .render(graphics, &map.tilesets, pos, layernum as f32)
-                            .expect("Chunk rendering should not fail");
-                    }
-                }
-            }
-        }
+    // The arena is only drawn once it has finished loading
+    if let Some(arena) = data.services.assets.get_mut(&data.services.map) {
+        draw_terrain(arena, graphics, data.services.time.this_frame);
     }
 
     let def_tex = graphics.textures.get(Default::default()).unwrap();
@@ -172,3 +148,54 @@ fn process(r: &mut RenderSprites, entities: EntityIter, data: &mut Data) {
         v.clear();
     }
 }
+
+fn draw_terrain(arena: &mut MapAsset, graphics: &mut GraphicsState, time: f64) {
+    let MapAsset { map, gpu } = arena;
+    gpu.update_animations(map, time)
+        .expect("Tile animations should be able to update");
+    let map = &*map;
+
+    // Draw terrain chunks
+    let viewport = graphics.camera.world_viewport();
+    for (layernum, layer) in map.layers.iter().enumerate() {
+        use crate::tiled::map::layer::Layer;
+        use crate::tiled::map::tiledata::TileData;
+        use math2d::RectCorner::{BottomLeft, BottomRight, TopLeft, TopRight};
+
+        match layer {
+            Layer::Tile(layer) => {
+                if !layer.visible {
+                    continue;
+                }
+
+                // On diamond maps the viewport covers a rotated rect of tiles, so take the
+                // chunks spanned by all four corners
+                let viewport = viewport.translated_by(-layer.offset);
+                let corners = [TopLeft, TopRight, BottomLeft, BottomRight]
+                    .iter()
+                    .map(|&corner| {
+                        let tile = layer
+                            .data
+                            .tile_pos_at(map.orientation, viewport.corner(corner));
+                        TileData::chunk_pos(tile)
+                    })
+                    .collect::<Vec<_>>();
+                let min_x = corners.iter().map(|c| c.x).min().unwrap();
+                let max_x = corners.iter().map(|c| c.x).max().unwrap();
+                let min_y = corners.iter().map(|c| c.y).min().unwrap();
+                let max_y = corners.iter().map(|c| c.y).max().unwrap();
+                for y in min_y..=max_y {
+                    for x in min_x..=max_x {
+                        gpu.render_chunk(graphics, map, layernum, (x, y).into())
+                            .expect("Chunk rendering should not fail");
+                    }
+                }
+            }
+            Layer::Image(_) => {
+                gpu.render_image_layer(graphics, map, layernum)
+                    .expect("Image layer rendering should not fail");
+            }
+            _ => (),
+        }
+    }
+}
@@ -1,9 +1,6 @@
-use crate::components::Sprite;
 use crate::graphics::core::GraphicsCore;
 use crate::graphics::wrappers::texture::TextureData;
 
-use std::collections::HashMap;
-
 use failure::Fallible;
 use glium::texture::{MipmapsOption, RawImage2d};
 use index_pool::IndexPool;
@@ -20,7 +17,6 @@ pub struct TextureManager {
     temp_free: IndexPool,
 
     perm_textures: Vec<Texture>,
-    assets: HashMap<Box<str>, TextureId>,
 }
 
 const TEMP_MASK: u32 = 0x8000_0000;
@@ -35,33 +31,21 @@ impl TextureManager {
         Ok(tm)
     }
 
-    pub fn load_simple(&mut self, asset: &str, core: &GraphicsCore) -> Fallible<Sprite> {
-        use glium::texture::MipmapsOption::AutoGeneratedMipmaps;
-        use std::path::Path;
-
-        let id = if let Some(id) = self.assets.get(asset) {
-            *id
-        } else {
-            // TODO: Some other asset loading mechanism
-            let path = Path::new("./assets/textures").join(asset);
-            let img = image::open(path)?;
-            let rgba = img.to_rgba();
-            let dims = rgba.dimensions();
-            let data = TextureData::new(
-                core,
-                vec![RawImage2d::from_raw_rgba(rgba.into_raw(), dims)],
-                AutoGeneratedMipmaps,
-            )?;
-            let tex = Texture::new(data, 1, 1, true);
-            let id = self.insert(tex, true);
-            self.assets.insert(asset.into(), id);
-            id
-        };
-
-        Ok(Sprite {
-            texture: id,
-            subtexture: Default::default(),
-        })
+    /// Uploads a single pixel-art image. Only non-permanent textures can be freed.
+    pub fn insert_rgba(
+        &mut self,
+        core: &GraphicsCore,
+        rgba: image::RgbaImage,
+        permanent: bool,
+    ) -> Fallible<TextureId> {
+        let dims = rgba.dimensions();
+        let data = TextureData::new(
+            core,
+            vec![RawImage2d::from_raw_rgba(rgba.into_raw(), dims)],
+            MipmapsOption::AutoGeneratedMipmaps,
+        )?;
+        let tex = Texture::new(data, 1, 1, true);
+        Ok(self.insert(tex, permanent))
     }
 
     pub fn get(&self, id: TextureId) -> Option<&Texture> {
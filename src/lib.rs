@@ -0,0 +1,29 @@
+#![feature(range_contains, euclidean_division)]
+
+#[macro_use]
+extern crate hex_literal;
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate serde_derive;
+
+pub use components::Components;
+pub use services::Services;
+pub use systems::Systems;
+
+pub mod assets;
+pub mod components;
+pub mod graphics;
+pub mod input;
+pub mod physics;
+pub mod services;
+pub mod systems;
+pub mod tiled;
+
+mod helpers;
+
+pub type World = conniecs::World<Systems>;
+type Comps<T> = conniecs::ComponentList<Components, T>;
+type EntityIter<'a> = conniecs::EntityIter<'a, Components>;
+type Data = conniecs::DataHelper<Components, Services>;
+//type EntityData<'a> = conniecs::EntityData<'a, components::Components>;
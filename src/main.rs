@@ -1,73 +1,82 @@
-#![feature(range_contains, euclidean_division)]
+use pixel_arena_game::assets::{self, Handle, LoadState, MapAsset, TextureAsset};
+use pixel_arena_game::tiled::map::layer::Layer;
+use pixel_arena_game::{graphics, physics, services, Services, World};
 
-#[macro_use]
-extern crate hex_literal;
-#[macro_use]
-extern crate serde_derive;
-
-use failure::Fallible;
-
-pub use components::Components;
-pub use services::Services;
-pub use systems::Systems;
-
-pub mod assets;
-pub mod components;
-pub mod graphics;
-pub mod input;
-pub mod physics;
-pub mod services;
-pub mod systems;
-pub mod tiled;
-
-mod helpers;
-
-type World = conniecs::World<Systems>;
-type Comps<T> = conniecs::ComponentList<Components, T>;
-type EntityIter<'a> = conniecs::EntityIter<'a, Components>;
-type Data = conniecs::DataHelper<Components, Services>;
-//type EntityData<'a> = conniecs::EntityData<'a, components::Components>;
+use failure::{err_msg, Fallible};
 
 fn main() -> Fallible<()> {
-    let graphics = graphics::GraphicsState::new()?;
-
-    let mut box2d = physics::World::new(&[0.0, -10.0].into());
-    let map = {
-        use crate::tiled::source::Source;
-
-        let src = Source::new_file("assets/maps/placeholder/simple-grass-test.tmx");
-        let mut map = tiled::load_tmx(src)?;
-
-        map.tilesets.initialize(&graphics.core)?;
+    for skipped in assets::vfs::current().skipped() {
+        eprintln!("Not loading {}: {}", skipped.path.display(), skipped.error);
+    }
 
-        for layer in map.layers.iter_mut() {
-            if let tiled::map::layer::Layer::Tile(layer) = layer {
-                for (cpos, chunk) in layer.data.chunks.iter_mut() {
-                    use tiled::map::tiledata::CHUNK_SIZE;
-                    let pos =
-                        (cpos.to_f32().to_vector() * [1.0, -1.0] * CHUNK_SIZE as f32).to_point();
-                    chunk.create_physics(&map.tilesets, &pos, &mut box2d);
-                }
-            }
-        }
+    let graphics = graphics::GraphicsState::new()?;
 
-        map
-    };
+    // Loading happens in the background, the arena and player show up once they're ready
+    let mut assets = assets::AssetManager::new()?;
+    let map = assets.load::<MapAsset>("maps/placeholder/simple-grass-test.tmx");
+    let player_texture = assets.load::<TextureAsset>("textures/characters/playertemp.png");
 
     // Create core services
     let services = Services {
         graphics,
+        assets,
         quit_flag: false,
         jump: false,
         time: services::time::Time::new(),
         map,
-        box2d,
+        box2d: physics::World::new(&[0.0, -10.0].into()),
     };
 
     let mut world: World = conniecs::World::with_services(services);
 
-    world.data.create_entity(|e, c, s| {
-        use crate::components::Transform;
+    let mut arena_ready = false;
+    let mut player_ready = false;
+    while !world.data.services.quit_flag {
+        world.update();
+
+        if !arena_ready {
+            arena_ready = create_arena_physics(&mut world.data.services)?;
+        }
+        if !player_ready {
+            player_ready = spawn_player(&mut world, &player_texture)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Gives the arena's tiles their colliders once it has loaded. Returns whether it has.
+fn create_arena_physics(services: &mut Services) -> Fallible<bool> {
+    if !loaded(&services.assets, &services.map)? {
+        return Ok(false);
+    }
+
+    let map = &mut services.assets.get_mut(&services.map).unwrap().map;
+    for layer in map.layers.iter_mut() {
+        if let Layer::Tile(layer) = layer {
+            for (&cpos, chunk) in layer.data.chunks.iter_mut() {
+                chunk.create_physics(
+                    &map.tilesets,
+                    map.orientation,
+                    cpos,
+                    layer.offset,
+                    &mut services.box2d,
+                );
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Creates the player once its texture has loaded. Returns whether it has.
+fn spawn_player(world: &mut World, texture: &Handle<TextureAsset>) -> Fallible<bool> {
+    if !loaded(&world.data.services.assets, texture)? {
+        return Ok(false);
+    }
+
+    let sprite = world.data.services.assets.get(texture).unwrap().sprite();
+    world.data.create_entity(|e, c, _| {
+        use pixel_arena_game::components::Transform;
 
         let transform = Transform {
             scale: [0.5, 0.5].into(),
@@ -76,19 +85,25 @@ fn main() -> Fallible<()> {
             ..Default::default()
         };
 
-        let tm = &mut s.graphics.textures;
-        let core = &s.graphics.core;
-        let sprite = tm.load_simple("characters/playertemp.png", core).unwrap();
-
         c.transform.add(e, transform);
         c.sprite.add(e, sprite);
         c.shadow.add(e, Default::default());
         c.player.add(e, ());
     });
+    Ok(true)
+}
 
-    while !world.data.services.quit_flag {
-        world.update();
+/// Whether `handle` has finished loading, reporting any warnings once it has. Loading errors
+/// are fatal since the game can't run without its assets.
+fn loaded<T: assets::Asset>(assets: &assets::AssetManager, handle: &Handle<T>) -> Fallible<bool> {
+    match assets.state(handle) {
+        LoadState::Loading => Ok(false),
+        LoadState::Loaded => {
+            for warning in assets.warnings(handle) {
+                eprintln!("{}", warning);
+            }
+            Ok(true)
+        }
+        LoadState::Failed => Err(err_msg(assets.error(handle).unwrap().to_string())),
     }
-
-    Ok(())
 }
@@ -7,7 +7,9 @@ pub struct Services {
     pub quit_flag: bool,
     pub jump: bool,
     pub graphics: GraphicsState,
+    pub assets: crate::assets::AssetManager,
     pub time: time::Time,
-    pub map: crate::tiled::map::Map,
+    /// The arena, drawn once it has loaded
+    pub map: crate::assets::Handle<crate::assets::MapAsset>,
     pub box2d: crate::physics::World,
 }
@@ -1,9 +1,11 @@
+use crate::assets;
 use crate::graphics::systems as graphics;
 use crate::services;
 
 #[derive(conniecs::SystemManager)]
 pub struct Systems {
     update_time: services::time::UpdateTime,
+    update_assets: assets::UpdateAssets,
 
     begin_draw: graphics::BeginDraw,
     render_sprites: graphics::RenderSprites,
@@ -0,0 +1,155 @@
+//! Prebaked maps. A binmap is a small uncompressed header followed by the gzip'd bincode of
+//! `map::Map`:
+//!
+//! | bytes | contents                                                 |
+//! |-------|----------------------------------------------------------|
+//! | 8     | `MAGIC`                                                  |
+//! | 4     | format version (u32 LE)                                  |
+//! | 2 + n | engine version that wrote the file (u16 LE length, utf8) |
+//! | 8     | FNV-1a hash of the uncompressed bincode (u64 LE)         |
+//! | rest  | gzip'd bincode                                           |
+//!
+//! Files written before the header existed start straight with the gzip stream and are read as
+//! version 0. Bump `FORMAT_VERSION` whenever a change to the map types alters their bincode
+//! layout, and keep a copy of the old layout around (like `v0`) so older files can be upgraded.
+
+use crate::tiled::map::Map;
+
+use std::io::{Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use failure::{err_msg, Fallible, ResultExt};
+
+pub mod v0;
+
+pub const MAGIC: &[u8; 8] = b"ARENAMAP";
+pub const FORMAT_VERSION: u32 = 1;
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub format_version: u32,
+    pub engine_version: String,
+    pub content_hash: u64,
+}
+
+pub fn save(mut writer: impl Write, map: &Map) -> Fallible<()> {
+    let payload = bincode::serialize(map)?;
+    let header = Header {
+        format_version: FORMAT_VERSION,
+        engine_version: ENGINE_VERSION.into(),
+        content_hash: content_hash(&payload),
+    };
+
+    header.write(&mut writer)?;
+    let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::best());
+    encoder.write_all(&payload)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+pub fn load(mut reader: impl Read) -> Fallible<Map> {
+    let mut magic = [0; 8];
+    reader
+        .read_exact(&mut magic)
+        .context("Reading binmap header")?;
+
+    // Headerless files from before versioning
+    if magic[..2] == GZIP_MAGIC {
+        let reader = std::io::Cursor::new(magic).chain(reader);
+        let mut payload = Vec::new();
+        flate2::read::GzDecoder::new(reader).read_to_end(&mut payload)?;
+        return upgrade(0, &payload);
+    }
+
+    let header = Header::read_after_magic(&magic, &mut reader)?;
+    if header.format_version == 0 || header.format_version > FORMAT_VERSION {
+        return Err(err_msg(format!(
+            "binmap format version {} (written by engine {}) is not supported, this engine \
+             (version {}) reads versions up to {}",
+            header.format_version, header.engine_version, ENGINE_VERSION, FORMAT_VERSION,
+        )));
+    }
+
+    let mut payload = Vec::new();
+    flate2::read::GzDecoder::new(reader)
+        .read_to_end(&mut payload)
+        .context("Decompressing binmap")?;
+    if content_hash(&payload) != header.content_hash {
+        return Err(err_msg(
+            "binmap is corrupt, its contents don't match its hash",
+        ));
+    }
+
+    upgrade(header.format_version, &payload)
+}
+
+/// Reads just the header, e.g. to check whether a prebaked map needs rebuilding. Files from
+/// before versioning have no header and return None.
+pub fn read_header(mut reader: impl Read) -> Fallible<Option<Header>> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if magic[..2] == GZIP_MAGIC {
+        return Ok(None);
+    }
+    Ok(Some(Header::read_after_magic(&magic, &mut reader)?))
+}
+
+/// Deserializes a payload written with format `version`, upgrading it to the current types
+fn upgrade(version: u32, payload: &[u8]) -> Fallible<Map> {
+    let context = || format!("Reading binmap format version {}", version);
+    let map = match version {
+        0 => {
+            let map: v0::Map = bincode::deserialize(payload).with_context(|_| context())?;
+            map.upgrade()?
+        }
+        FORMAT_VERSION => bincode::deserialize(payload).with_context(|_| context())?,
+        _ => unreachable!("binmap version was checked against FORMAT_VERSION"),
+    };
+    map.validate().with_context(|_| context())?;
+    Ok(map)
+}
+
+impl Header {
+    fn write(&self, writer: &mut impl Write) -> Fallible<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LE>(self.format_version)?;
+        writer.write_u16::<LE>(self.engine_version.len() as u16)?;
+        writer.write_all(self.engine_version.as_bytes())?;
+        writer.write_u64::<LE>(self.content_hash)?;
+        Ok(())
+    }
+
+    fn read_after_magic(magic: &[u8; 8], reader: &mut impl Read) -> Fallible<Header> {
+        if magic != MAGIC {
+            return Err(err_msg("Not a binmap file"));
+        }
+
+        let format_version = reader.read_u32::<LE>()?;
+        let len = reader.read_u16::<LE>()? as usize;
+        let mut engine_version = vec![0; len];
+        reader.read_exact(&mut engine_version)?;
+        let engine_version =
+            String::from_utf8(engine_version).context("Invalid engine version in binmap")?;
+        let content_hash = reader.read_u64::<LE>()?;
+
+        Ok(Header {
+            format_version,
+            engine_version,
+            content_hash,
+        })
+    }
+}
+
+/// 64-bit FNV-1a. Not cryptographic, just stable across builds so prebaked maps can be checked
+/// for corruption and compared without loading them.
+pub fn content_hash(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,139 @@
+//! The layout maps were saved in before binmaps had a header. Only the types that changed
+//! since are copied here, the rest are still read with the current definitions.
+
+use crate::tiled::map::layer::{Layer as CurrentLayer, LayerFlags, TileLayer as CurrentTileLayer};
+use crate::tiled::map::tiledata::TileData;
+use crate::tiled::map::tilesets::Tilesets as CurrentTilesets;
+use crate::tiled::map::{Map as CurrentMap, Orientation, RenderOrder};
+use crate::tiled::tileset::image::Image;
+use crate::tiled::tileset::tile::{Collider, Tile as CurrentTile, TileFlags};
+use crate::tiled::tileset::{GridLayout, Tileset as CurrentTileset};
+
+use std::ops::Range;
+
+use failure::{Fallible, ResultExt};
+use math2d::Vector2f;
+
+#[derive(Deserialize)]
+pub struct Map {
+    pub tilesets: Tilesets,
+    pub layers: Vec<Layer>,
+    pub tile_size: Vector2f,
+}
+
+#[derive(Deserialize)]
+pub struct Tilesets {
+    pub tilesets: Vec<(Range<u32>, Tileset)>,
+}
+
+#[derive(Deserialize)]
+pub struct Tileset {
+    pub tile_scale: Vector2f,
+    pub tile_width: u16,
+    pub tile_height: u16,
+    pub rows: u8,
+    pub columns: u8,
+    pub margin: u8,
+    pub spacing: u8,
+
+    pub image: Image,
+    pub tiles: Box<[Tile]>,
+}
+
+#[derive(Deserialize)]
+pub struct Tile {
+    pub flags: TileFlags,
+    pub colliders: Box<[Collider]>,
+}
+
+#[derive(Deserialize)]
+pub enum Layer {
+    Tile(TileLayer),
+    Unused,
+}
+
+#[derive(Deserialize)]
+pub struct TileLayer {
+    pub flags: LayerFlags,
+    pub opacity: f32,
+    pub visible: bool,
+    pub data: TileData,
+}
+
+impl Map {
+    /// Fills in everything added since with what the loaders produce for a plain orthogonal
+    /// map made in Tiled
+    pub fn upgrade(self) -> Fallible<CurrentMap> {
+        let tilesets = self
+            .tilesets
+            .tilesets
+            .into_iter()
+            .map(|(range, tileset)| Ok((range, tileset.upgrade()?)))
+            .collect::<Fallible<Vec<_>>>()?;
+        let tilesets = CurrentTilesets::from_list(tilesets)?;
+
+        let layers = self
+            .layers
+            .into_iter()
+            .enumerate()
+            .map(|(i, layer)| match layer {
+                Layer::Tile(layer) => CurrentLayer::Tile(CurrentTileLayer {
+                    name: format!("Layer {}", i + 1),
+                    flags: layer.flags,
+                    offset: Vector2f::new(0.0, 0.0),
+                    opacity: layer.opacity,
+                    visible: layer.visible,
+                    properties: Default::default(),
+                    data: layer.data,
+                }),
+                Layer::Unused => CurrentLayer::Unused,
+            })
+            .collect();
+
+        let mut map = CurrentMap {
+            tilesets,
+            layers,
+            tile_size: self.tile_size,
+            properties: Default::default(),
+            background_color: None,
+            width: 0,
+            height: 0,
+            infinite: true,
+            orientation: Orientation::Orthogonal,
+            render_order: RenderOrder::RightDown,
+        };
+        if let Some(bounds) = map.tile_bounds() {
+            map.width = bounds.right.max(0);
+            map.height = bounds.bottom.max(0);
+        }
+        Ok(map)
+    }
+}
+
+impl Tileset {
+    fn upgrade(self) -> Fallible<CurrentTileset> {
+        let grid = GridLayout {
+            tile_width: self.tile_width,
+            tile_height: self.tile_height,
+            columns: self.columns,
+            rows: self.rows,
+            margin: self.margin,
+            spacing: self.spacing,
+        };
+        let tiles = self
+            .tiles
+            .into_vec()
+            .into_iter()
+            .map(|tile| CurrentTile {
+                flags: tile.flags,
+                colliders: tile.colliders,
+                animation: Default::default(),
+                properties: Default::default(),
+            })
+            .collect();
+
+        let tileset = CurrentTileset::from_grid(self.tile_scale, grid, self.image, tiles)
+            .context("Upgrading version 0 tileset")?;
+        Ok(tileset)
+    }
+}
@@ -0,0 +1,136 @@
+//! The GPU side of a map. `Map` is plain data so it can be loaded and have its images decoded on
+//! another thread; everything here is created from it on the main thread.
+
+use crate::graphics::core::GraphicsCore;
+use crate::graphics::GraphicsState;
+use crate::tiled::map::layer::Layer;
+use crate::tiled::map::tiledata::chunk::ChunkBuffers;
+use crate::tiled::map::Map;
+use crate::tiled::tileset::image::Image;
+use crate::tiled::tileset::TilesetGpu;
+
+use std::collections::HashMap;
+
+use failure::{Fallible, ResultExt};
+use glium::texture::SrgbTexture2d;
+use math2d::Point2i;
+
+/// The images of a map's tilesets and image layers, decoded to pixels for `MapGpu::upload`
+pub struct MapImages {
+    tilesets: Vec<image::RgbaImage>,
+    /// By layer index
+    layers: HashMap<usize, image::RgbaImage>,
+}
+
+pub struct MapGpu {
+    /// Indexed by `TilesetId` - 1
+    pub tilesets: Vec<TilesetGpu>,
+    /// Image layer textures by layer index
+    images: HashMap<usize, SrgbTexture2d>,
+    /// Tile layer buffers by layer index and chunk position, along with the chunk revision they
+    /// were built from. Chunks get their buffers the first time they're drawn.
+    chunks: HashMap<(usize, Point2i), (u32, ChunkBuffers)>,
+}
+
+impl MapImages {
+    /// Decodes every image in `map`. This is the slow part of getting a map ready to draw and
+    /// doesn't need the GPU.
+    pub fn decode(map: &Map) -> Fallible<MapImages> {
+        let tilesets = map
+            .tilesets
+            .decode_images()
+            .context("Decoding tileset images")?;
+
+        let mut layers = HashMap::new();
+        for (i, layer) in map.layers.iter().enumerate() {
+            if let Layer::Image(layer) = layer {
+                let rgba = layer
+                    .image
+                    .to_rgba()
+                    .context("Decoding image layer image")?;
+                layers.insert(i, rgba);
+            }
+        }
+
+        Ok(MapImages { tilesets, layers })
+    }
+}
+
+impl MapGpu {
+    /// Uploads the images from `MapImages::decode(map)`
+    pub fn upload(map: &mut Map, images: MapImages, core: &GraphicsCore) -> Fallible<MapGpu> {
+        let tilesets = map.tilesets.upload(core, images.tilesets)?;
+        let images = images
+            .layers
+            .into_iter()
+            .map(|(i, rgba)| Ok((i, Image::create_texture(core, rgba)?)))
+            .collect::<Fallible<HashMap<_, _>>>()
+            .context("Uploading image layer image")?;
+
+        Ok(MapGpu {
+            tilesets,
+            images,
+            chunks: HashMap::new(),
+        })
+    }
+
+    /// Advances animated tiles to their frame at `time`
+    pub fn update_animations(&self, map: &mut Map, time: f64) -> Fallible<()> {
+        map.tilesets.update_animations(&self.tilesets, time)
+    }
+
+    /// Draws the chunk at `cpos` of tile layer `index`, building its buffers first if it hasn't
+    /// been drawn since its tiles last changed
+    pub fn render_chunk(
+        &mut self,
+        graphics: &mut GraphicsState,
+        map: &Map,
+        index: usize,
+        cpos: Point2i,
+    ) -> Fallible<()> {
+        let layer = match &map.layers[index] {
+            Layer::Tile(layer) => layer,
+            _ => return Ok(()),
+        };
+        let chunk = match layer.data.chunks.get(&cpos) {
+            Some(chunk) => chunk,
+            None => return Ok(()),
+        };
+
+        let key = (index, cpos);
+        let stale = match self.chunks.get(&key) {
+            Some(&(revision, _)) => revision != chunk.revision(),
+            None => true,
+        };
+        if stale {
+            let buffers = chunk.create_buffers(&graphics.core)?;
+            self.chunks.insert(key, (chunk.revision(), buffers));
+        }
+
+        let (_, buffers) = &self.chunks[&key];
+        chunk.render(
+            graphics,
+            &map.tilesets,
+            &self.tilesets,
+            buffers,
+            map.orientation,
+            cpos,
+            layer.offset,
+            index as f32,
+        )
+    }
+
+    /// Draws image layer `index`
+    pub fn render_image_layer(
+        &self,
+        graphics: &mut GraphicsState,
+        map: &Map,
+        index: usize,
+    ) -> Fallible<()> {
+        if let (Layer::Image(layer), Some(texture)) = (&map.layers[index], self.images.get(&index))
+        {
+            layer.render(graphics, texture, index as f32)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use super::{pixel_to_world, Inherited};
+use crate::graphics::GraphicsState;
+use crate::tiled::map::properties::Properties;
+use crate::tiled::raw;
+use crate::tiled::tileset::image::Image;
+
+use failure::{Fallible, ResultExt};
+use glium::texture::SrgbTexture2d;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use math2d::{Point2f, Rectf, Vector2f};
+
+#[derive(Serialize, Deserialize)]
+pub struct ImageLayer {
+    pub name: String,
+    /// World position of the top-left corner of the image, before parallax is applied
+    pub position: Point2f,
+    /// Size of the image in world units
+    pub size: Vector2f,
+    pub opacity: f32,
+    pub visible: bool,
+    /// How fast the layer scrolls relative to the camera. `1.0` moves with the map and `0.0`
+    /// stays fixed on screen.
+    pub parallax: Vector2f,
+    pub repeat_x: bool,
+    pub repeat_y: bool,
+    pub properties: Properties,
+    pub image: Image,
+}
+
+impl ImageLayer {
+    pub fn from_raw(
+        raw: &raw::ImageLayer,
+        inherited: &Inherited,
+        tile_size: Vector2f,
+    ) -> Fallible<Self> {
+        let name = inherited.name(&raw.name);
+        let image = Image::from_raw(Some(&raw.image)).context("Loading image layer image")?;
+
+        let offset = inherited.offset(raw.offsetx, raw.offsety);
+        let position = pixel_to_world(offset.to_point(), tile_size);
+        let size = Vector2f::new(image.width as f32, image.height as f32) / tile_size;
+        let opacity = inherited.opacity(raw.opacity);
+        let visible = inherited.visible(raw.visible);
+        let parallax = Vector2f::new(raw.parallaxx, raw.parallaxy);
+        let repeat_x = raw.repeatx;
+        let repeat_y = raw.repeaty;
+        let properties = Properties::from_raw(&inherited.properties(&raw.properties));
+
+        Ok(ImageLayer {
+            name,
+            position,
+            size,
+            opacity,
+            visible,
+            parallax,
+            repeat_x,
+            repeat_y,
+            properties,
+            image,
+        })
+    }
+
+    /// The world-space rect the image covers when the camera is centered on `camera_pos`
+    pub fn world_rect(&self, camera_pos: Vector2f) -> Rectf {
+        let shift = camera_pos * (Vector2f::new(1.0, 1.0) - self.parallax);
+        let topleft = self.position + shift;
+        let left = topleft.x;
+        let top = topleft.y;
+        Rectf::new(left, top, left + self.size.x, top - self.size.y)
+    }
+
+    /// Draws the layer with `texture`, its image uploaded with `Image::create_texture`
+    pub fn render(
+        &self,
+        graphics: &mut GraphicsState,
+        texture: &SrgbTexture2d,
+        layer: f32,
+    ) -> Fallible<()> {
+        use glium::index::{NoIndices, PrimitiveType};
+        use glium::{uniform, DrawParameters, Surface};
+
+        if !self.visible {
+            return Ok(());
+        }
+
+        let camera_pos = graphics.camera.position + graphics.camera.offset;
+        let viewport = graphics.camera.world_viewport();
+        let image_rect = self.world_rect(camera_pos);
+
+        // Repeating axes just cover the whole viewport and let the sampler wrap
+        let mut rect = image_rect;
+        if self.repeat_x {
+            rect.left = viewport.left;
+            rect.right = viewport.right;
+        }
+        if self.repeat_y {
+            rect.top = viewport.top;
+            rect.bottom = viewport.bottom;
+        }
+
+        if !rect.normalized().overlaps(&viewport.normalized()) {
+            return Ok(());
+        }
+
+        let uvrect = [
+            (rect.left - image_rect.left) / self.size.x,
+            (image_rect.top - rect.top) / self.size.y,
+            (rect.right - image_rect.left) / self.size.x,
+            (image_rect.top - rect.bottom) / self.size.y,
+        ];
+        let wrap = |repeat| {
+            if repeat {
+                SamplerWrapFunction::Repeat
+            } else {
+                SamplerWrapFunction::Clamp
+            }
+        };
+
+        let mut tex = texture
+            .sampled()
+            .minify_filter(MinifySamplerFilter::Linear)
+            .magnify_filter(MagnifySamplerFilter::Nearest)
+            .anisotropy(8);
+        tex.1.wrap_function = (
+            wrap(self.repeat_x),
+            wrap(self.repeat_y),
+            SamplerWrapFunction::Clamp,
+        );
+
+        let camera = graphics.camera.buffer();
+        let shader = &graphics.shaders.image_layer;
+        let frame = graphics.frame.gameplay_frame().unwrap();
+
+        frame.draw(
+            &graphics.core.quad,
+            NoIndices(PrimitiveType::TrianglesList),
+            &shader.program,
+            &uniform! {
+                Camera: camera,
+                tex: tex,
+                rect: [rect.left, rect.top, rect.right, rect.bottom],
+                uvrect: uvrect,
+                opacity: self.opacity,
+                layer: layer,
+            },
+            &DrawParameters {
+                depth: glium::Depth {
+                    test: glium::DepthTest::IfMoreOrEqual,
+                    write: true,
+                    ..Default::default()
+                },
+                blend: glium::Blend::alpha_blending(),
+                ..Default::default()
+            },
+        )?;
+
+        Ok(())
+    }
+}
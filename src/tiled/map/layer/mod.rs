@@ -0,0 +1,309 @@
+pub use self::image::ImageLayer;
+pub use self::object::ObjectLayer;
+
+use crate::tiled::map::properties::Properties;
+use crate::tiled::map::tiledata::{chunk::Chunk, TileData, CHUNK_SIZE};
+use crate::tiled::map::tilesets::Tilesets;
+use crate::tiled::map::TileId;
+use crate::tiled::raw;
+
+use std::collections::HashMap;
+
+use failure::{err_msg, Fallible};
+use math2d::{Point2f, Point2i, Vector2f};
+
+pub mod image;
+pub mod object;
+
+#[derive(Serialize, Deserialize)]
+pub enum Layer {
+    Tile(TileLayer),
+    Object(ObjectLayer),
+    Image(ImageLayer),
+    Unused,
+}
+
+impl Layer {
+    /// Converts `raw` into runtime layers and appends them to `layers`. Group layers are
+    /// flattened away, with their offset, opacity, visibility and properties folded into
+    /// their children.
+    pub fn flatten(
+        raw: &raw::Layer,
+        inherited: &Inherited,
+        sets: &Tilesets,
+        tile_size: Vector2f,
+        layers: &mut Vec<Layer>,
+    ) -> Fallible<()> {
+        let layer = match raw {
+            raw::Layer::Tile(raw) => {
+                Layer::Tile(TileLayer::from_raw(raw, inherited, sets, tile_size)?)
+            }
+            raw::Layer::Object(raw) => {
+                Layer::Object(ObjectLayer::from_raw(raw, inherited, sets, tile_size)?)
+            }
+            raw::Layer::Image(raw) => {
+                Layer::Image(ImageLayer::from_raw(raw, inherited, tile_size)?)
+            }
+            raw::Layer::Group(raw) => {
+                let inherited = inherited.enter(raw);
+                for child in raw.layers.iter() {
+                    Layer::flatten(child, &inherited, sets, tile_size, layers)?;
+                }
+                return Ok(());
+            }
+        };
+
+        layers.push(layer);
+        Ok(())
+    }
+
+    /// The full name of the layer, including the names of any groups it was nested in
+    /// (e.g. `Walls/Upper`)
+    pub fn name(&self) -> &str {
+        match self {
+            Layer::Tile(layer) => &layer.name,
+            Layer::Object(layer) => &layer.name,
+            Layer::Image(layer) => &layer.name,
+            Layer::Unused => "",
+        }
+    }
+
+    pub fn validate(&self, sets: &Tilesets) -> Fallible<()> {
+        match self {
+            Layer::Tile(layer) => layer.validate(sets),
+            Layer::Object(layer) => layer.validate(sets),
+            Layer::Image(_) => Ok(()),
+            Layer::Unused => unimplemented!(),
+        }
+    }
+}
+
+/// Everything a layer inherits from the group layers it is nested in
+#[derive(Clone)]
+pub struct Inherited {
+    pub path: String,
+    /// Offset in pixels
+    pub offset: Vector2f,
+    pub opacity: f32,
+    pub visible: bool,
+    pub properties: raw::Properties,
+    /// Whether the map is infinite, where tile layers may have no data at all
+    pub infinite: bool,
+}
+
+impl Default for Inherited {
+    fn default() -> Self {
+        Inherited {
+            path: String::new(),
+            offset: [0.0, 0.0].into(),
+            opacity: 1.0,
+            visible: true,
+            properties: Default::default(),
+            infinite: false,
+        }
+    }
+}
+
+impl Inherited {
+    pub fn enter(&self, group: &raw::GroupLayer) -> Inherited {
+        Inherited {
+            path: self.name(&group.name),
+            offset: self.offset(group.offsetx, group.offsety),
+            opacity: self.opacity(group.opacity),
+            visible: self.visible(group.visible),
+            properties: self.properties(&group.properties),
+            infinite: self.infinite,
+        }
+    }
+
+    pub fn name(&self, name: &str) -> String {
+        if self.path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.path, name)
+        }
+    }
+
+    pub fn offset(&self, offsetx: f32, offsety: f32) -> Vector2f {
+        self.offset + [offsetx, offsety]
+    }
+
+    pub fn opacity(&self, opacity: f32) -> f32 {
+        self.opacity * opacity
+    }
+
+    pub fn visible(&self, visible: bool) -> bool {
+        self.visible && visible
+    }
+
+    /// Layer properties override the group's, except for `flags` which are combined
+    pub fn properties(&self, props: &raw::Properties) -> raw::Properties {
+        let mut result = self.properties.clone();
+        for (name, prop) in props.properties.iter() {
+            let prop = match (name.as_str(), result.properties.get(name), prop) {
+                ("flags", Some(raw::Property::String(outer)), raw::Property::String(inner)) => {
+                    raw::Property::String(format!("{}|{}", outer, inner))
+                }
+                _ => prop.clone(),
+            };
+            result.properties.insert(name.clone(), prop);
+        }
+        result
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TileLayer {
+    pub name: String,
+    pub flags: LayerFlags,
+    /// Offset of the whole layer in world units
+    pub offset: Vector2f,
+    pub opacity: f32,
+    pub visible: bool,
+    pub properties: Properties,
+    pub data: TileData,
+}
+
+impl TileLayer {
+    pub fn from_raw(
+        raw: &raw::TileLayer,
+        inherited: &Inherited,
+        sets: &Tilesets,
+        tile_size: Vector2f,
+    ) -> Fallible<Self> {
+        let name = inherited.name(&raw.name);
+        let properties = inherited.properties(&raw.properties);
+        let flags = LayerFlags::from_raw(&properties)?;
+        let properties = Properties::from_raw(&properties);
+        let offset = inherited.offset(raw.offsetx, raw.offsety) * [1.0, -1.0] / tile_size;
+        let opacity = inherited.opacity(raw.opacity);
+        let visible = inherited.visible(raw.visible);
+
+        // Tiled's chunks can be any size and offset, so re-bucket them into our own chunk grid.
+        // Fixed-size maps are just one big chunk at the origin.
+        let mut buckets = HashMap::new();
+        match &raw.data {
+            // Infinite maps write layers without any chunks as an empty `<data>`
+            raw::Data::Plain(data) if data.is_empty() && inherited.infinite => (),
+            raw::Data::Plain(data) => {
+                let origin = (0, 0).into();
+                bucket_tiles(&mut buckets, sets, origin, raw.width, raw.height, data)?;
+            }
+            raw::Data::Chunked(rawchunks) => {
+                for raw in rawchunks.iter() {
+                    let origin = (raw.x, raw.y).into();
+                    bucket_tiles(&mut buckets, sets, origin, raw.width, raw.height, &raw.data)?;
+                }
+            }
+        }
+
+        let mut chunks = HashMap::with_capacity(buckets.len());
+        for (pos, data) in buckets {
+            let chunk = Chunk::new(data);
+            chunk.validate(sets)?;
+            chunks.insert(pos, chunk);
+        }
+
+        let data = TileData { chunks };
+
+        Ok(TileLayer {
+            name,
+            flags,
+            offset,
+            opacity,
+            visible,
+            properties,
+            data,
+        })
+    }
+
+    pub fn validate(&self, sets: &Tilesets) -> Fallible<()> {
+        self.data.validate(sets)?;
+
+        Ok(())
+    }
+}
+
+/// Converts Tiled pixel coordinates into world space. Tile centers sit on whole world
+/// coordinates with y pointing up, so the top-left corner of tile (0, 0) is at (-0.5, 0.5).
+fn pixel_to_world(pixel: Point2f, tile_size: Vector2f) -> Point2f {
+    let x = pixel.x / tile_size.x - 0.5;
+    let y = -pixel.y / tile_size.y + 0.5;
+    (x, y).into()
+}
+
+/// Sorts a `width`x`height` block of tiles whose top-left tile sits at `origin` into
+/// `CHUNK_SIZE`x`CHUNK_SIZE` buckets keyed by chunk position. Buckets are padded with the empty
+/// tile, and a bucket is only created once a non-empty tile lands in it.
+fn bucket_tiles(
+    buckets: &mut HashMap<Point2i, Vec<TileId>>,
+    sets: &Tilesets,
+    origin: Point2i,
+    width: i32,
+    height: i32,
+    data: &[raw::GlobalTileId],
+) -> Fallible<()> {
+    if width < 0 || height < 0 || data.len() != (width * height) as usize {
+        return Err(err_msg(format!(
+            "Tile data has {} tiles but should be {}x{}",
+            data.len(),
+            width,
+            height
+        )));
+    }
+
+    for (i, &gid) in data.iter().enumerate() {
+        let tile = sets.tile_from_raw(gid);
+        if tile == TileId::default() {
+            continue;
+        }
+
+        let x = origin.x + i as i32 % width;
+        let y = origin.y + i as i32 / width;
+        let cpos = TileData::chunk_pos((x, y).into());
+        let lx = x - cpos.x * CHUNK_SIZE;
+        let ly = y - cpos.y * CHUNK_SIZE;
+
+        let bucket = buckets
+            .entry(cpos)
+            .or_insert_with(|| vec![TileId::default(); (CHUNK_SIZE * CHUNK_SIZE) as usize]);
+        bucket[(ly * CHUNK_SIZE + lx) as usize] = tile;
+    }
+
+    Ok(())
+}
+
+#[auto_enum::enum_flags(u32)]
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub enum LayerFlags {
+    NOCOLLIDE,
+
+    NONE = 0,
+}
+
+impl LayerFlags {
+    pub fn from_raw(props: &raw::Properties) -> Fallible<LayerFlags> {
+        let mut flags = LayerFlags::NONE;
+        if let Some(raw::Property::String(prop)) = props.properties.get("flags") {
+            for flag in prop.split('|') {
+                flags |= match flag.trim() {
+                    "NOCOLLIDE" => LayerFlags::NOCOLLIDE,
+
+                    "" | "NONE" => LayerFlags::NONE,
+                    _ => return Err(err_msg(format!("Unknown layer flag `{}`", flag))),
+                };
+            }
+        }
+        Ok(flags)
+    }
+
+    /// The `flags` property value these flags were loaded from
+    pub fn to_raw(self) -> String {
+        if self.is_set(LayerFlags::NOCOLLIDE) {
+            "NOCOLLIDE".into()
+        } else {
+            String::new()
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use super::{pixel_to_world, Inherited};
+use crate::tiled::map::properties::Properties;
+use crate::tiled::map::tilesets::Tilesets;
+use crate::tiled::map::TileId;
+use crate::tiled::raw;
+
+use failure::{err_msg, Fallible};
+use math2d::{Point2f, Vector2f};
+
+#[derive(Serialize, Deserialize)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub opacity: f32,
+    pub visible: bool,
+    pub properties: Properties,
+    pub objects: Vec<Object>,
+}
+
+impl ObjectLayer {
+    pub fn from_raw(
+        raw: &raw::ObjectGroup,
+        inherited: &Inherited,
+        sets: &Tilesets,
+        tile_size: Vector2f,
+    ) -> Fallible<Self> {
+        let name = inherited.name(raw.name.as_ref().map(|s| s.as_str()).unwrap_or(""));
+        let opacity = inherited.opacity(raw.opacity);
+        let visible = inherited.visible(raw.visible);
+        let properties = Properties::from_raw(&inherited.properties(&raw.properties));
+
+        let offset = inherited.offset(raw.offsetx, raw.offsety);
+        let objects: Fallible<_> = raw
+            .objects
+            .iter()
+            .map(|obj| Object::from_raw(obj, sets, tile_size, offset))
+            .collect();
+        let objects = objects?;
+
+        Ok(ObjectLayer {
+            name,
+            opacity,
+            visible,
+            properties,
+            objects,
+        })
+    }
+
+    pub fn validate(&self, sets: &Tilesets) -> Fallible<()> {
+        for object in self.objects.iter() {
+            object.validate(sets)?;
+        }
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Object> {
+        self.objects.iter().find(|obj| obj.name == name)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Object {
+    pub id: i32,
+    pub name: String,
+    pub kind: String,
+    /// Position in world space. Tiled anchors tile objects at their bottom-left corner and
+    /// everything else at the top-left corner.
+    pub position: Point2f,
+    /// Size in world units
+    pub size: Vector2f,
+    /// Clockwise rotation around `position` in radians
+    pub rotation: f32,
+    pub visible: bool,
+    pub shape: Shape,
+    pub tile: Option<TileId>,
+    pub properties: Properties,
+}
+
+impl Object {
+    pub fn from_raw(
+        raw: &raw::Object,
+        sets: &Tilesets,
+        tile_size: Vector2f,
+        offset: Vector2f,
+    ) -> Fallible<Self> {
+        use crate::tiled::raw::Shape as RawShape;
+
+        let id = raw.id;
+        let name = raw.name.clone().unwrap_or_default();
+        let kind = raw.kind.clone().unwrap_or_default();
+        let position = pixel_to_world(Point2f::new(raw.x, raw.y) + offset, tile_size);
+        let size = Vector2f::new(raw.width, raw.height) / tile_size;
+        let rotation = raw.rotation;
+        let visible = raw.visible;
+        let tile = raw.gid.map(|gid| sets.tile_from_raw(gid));
+        let properties = Properties::from_raw(&raw.properties);
+
+        let points = |points: &[Vector2f]| -> Box<[Vector2f]> {
+            points
+                .iter()
+                .map(|&p| Vector2f::new(p.x, -p.y) / tile_size)
+                .collect()
+        };
+        let shape = match &raw.shape {
+            RawShape::Rectangle => Shape::Rectangle,
+            RawShape::Ellipse => Shape::Ellipse,
+            RawShape::Point => Shape::Point,
+            RawShape::Polygon(rel_points) => Shape::Polygon(points(rel_points)),
+            RawShape::Polyline(rel_points) => Shape::Polyline(points(rel_points)),
+        };
+
+        Ok(Object {
+            id,
+            name,
+            kind,
+            position,
+            size,
+            rotation,
+            visible,
+            shape,
+            tile,
+            properties,
+        })
+    }
+
+    pub fn validate(&self, sets: &Tilesets) -> Fallible<()> {
+        if let Some(tile) = self.tile {
+            if tile != TileId::default() && sets.get_tile(tile).is_none() {
+                return Err(err_msg(format!(
+                    "Object {} references an invalid tile",
+                    self.id
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Shape {
+    /// Covers `size`, extending right and down from `position`
+    Rectangle,
+    /// Inscribed in the rectangle covered by `size`
+    Ellipse,
+    Point,
+    /// Points relative to `position` in world units
+    Polygon(Box<[Vector2f]>),
+    /// Points relative to `position` in world units
+    Polyline(Box<[Vector2f]>),
+}
@@ -1,11 +1,17 @@
-use self::layer::Layer;
+pub use self::orientation::{Orientation, StaggerAxis, StaggerIndex};
+
+use self::layer::{object::Object, Inherited, Layer};
+use self::properties::Properties;
 use self::tilesets::Tilesets;
 use crate::tiled::raw;
 
 use failure::Fallible;
-use math2d::Vector2f;
+use math2d::{Color, Matrix3x2f, Point2f, Rectf, Recti, Vector2f};
 
+pub mod gpu;
 pub mod layer;
+pub mod orientation;
+pub mod properties;
 pub mod tiledata;
 pub mod tilesets;
 
@@ -14,6 +20,14 @@ pub struct Map {
     pub tilesets: Tilesets,
     pub layers: Vec<Layer>,
     pub tile_size: Vector2f,
+    pub properties: Properties,
+    pub background_color: Option<Color>,
+    /// Size in tiles as set in Tiled. Infinite maps can extend past this, see `tile_bounds`.
+    pub width: i32,
+    pub height: i32,
+    pub infinite: bool,
+    pub orientation: Orientation,
+    pub render_order: RenderOrder,
 }
 
 impl Map {
@@ -21,20 +35,117 @@ impl Map {
         let tile_size = [raw.tilewidth as f32, raw.tileheight as f32].into();
 
         let tilesets = Tilesets::from_raw(&raw.tilesets, tile_size)?;
-        let layers: Fallible<_> = raw
-            .layers
-            .iter()
-            .map(|raw| Layer::from_raw(raw, &tilesets))
-            .collect();
-        let layers = layers?;
+        let mut layers = Vec::with_capacity(raw.layers.len());
+        let root = Inherited {
+            infinite: raw.infinite,
+            ..Default::default()
+        };
+        for layer in raw.layers.iter() {
+            Layer::flatten(layer, &root, &tilesets, tile_size, &mut layers)?;
+        }
+
+        let properties = Properties::from_raw(&raw.properties);
+        let orientation = Orientation::from_raw(raw)?;
+        let render_order = match raw.renderorder {
+            raw::RenderOrder::RightDown => RenderOrder::RightDown,
+            raw::RenderOrder::RightUp => RenderOrder::RightUp,
+            raw::RenderOrder::LeftDown => RenderOrder::LeftDown,
+            raw::RenderOrder::LeftUp => RenderOrder::LeftUp,
+        };
 
         Ok(Map {
             tilesets,
             layers,
             tile_size,
+            properties,
+            background_color: raw.backgroundcolor,
+            width: raw.width,
+            height: raw.height,
+            infinite: raw.infinite,
+            orientation,
+            render_order,
         })
     }
 
+    /// Looks up a layer by its full name, e.g. `Walls/Upper` for a layer nested in a group
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name() == name)
+    }
+
+    /// Bounds of every tile on every tile layer in tile coordinates (y pointing down), ignoring
+    /// layer offsets
+    pub fn tile_bounds(&self) -> Option<Recti> {
+        self.layers
+            .iter()
+            .filter_map(|layer| match layer {
+                Layer::Tile(layer) => layer.data.tile_bounds(),
+                _ => None,
+            })
+            .fold(None, |acc, b| match acc {
+                Some(acc) => Some(tiledata::union(acc, b)),
+                None => Some(b),
+            })
+    }
+
+    /// World-space rect covering every tile on every tile layer, including layer offsets.
+    /// `top` is above `bottom` since world y points up.
+    pub fn world_bounds(&self) -> Option<Rectf> {
+        self.layers
+            .iter()
+            .filter_map(|layer| match layer {
+                Layer::Tile(layer) => {
+                    let b = layer.data.tile_bounds()?;
+                    let off = layer.offset;
+                    // The outermost cells are always at the corners of the tile rect, or one in
+                    // from them on staggered maps
+                    let (mut min, mut max) = (
+                        Point2f::new(std::f32::INFINITY, std::f32::INFINITY),
+                        Point2f::new(std::f32::NEG_INFINITY, std::f32::NEG_INFINITY),
+                    );
+                    for &x in [b.left, b.left + 1, b.right - 2, b.right - 1].iter() {
+                        for &y in [b.top, b.top + 1, b.bottom - 2, b.bottom - 1].iter() {
+                            let x = x.max(b.left).min(b.right - 1);
+                            let y = y.max(b.top).min(b.bottom - 1);
+                            let center = self.orientation.tile_to_world((x, y).into());
+                            min = Point2f::new(min.x.min(center.x), min.y.min(center.y));
+                            max = Point2f::new(max.x.max(center.x), max.y.max(center.y));
+                        }
+                    }
+                    Some(Rectf::new(
+                        min.x - 0.5 + off.x,
+                        max.y + 0.5 + off.y,
+                        max.x + 0.5 + off.x,
+                        min.y - 0.5 + off.y,
+                    ))
+                }
+                _ => None,
+            })
+            .fold(None, |acc: Option<Rectf>, b| match acc {
+                Some(acc) => Some(Rectf::new(
+                    acc.left.min(b.left),
+                    acc.top.max(b.top),
+                    acc.right.max(b.right),
+                    acc.bottom.min(b.bottom),
+                )),
+                None => Some(b),
+            })
+    }
+
+    /// Finds an object on any object layer, e.g. the target of an object property
+    pub fn object(&self, id: i32) -> Option<&Object> {
+        self.layers
+            .iter()
+            .filter_map(|layer| match layer {
+                Layer::Object(layer) => layer.objects.iter().find(|obj| obj.id == id),
+                _ => None,
+            })
+            .next()
+    }
+
     pub fn validate(&self) -> Fallible<()> {
         self.tilesets.validate()?;
         for layer in self.layers.iter() {
@@ -44,30 +155,91 @@ impl Map {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderOrder {
+    RightDown,
+    RightUp,
+    LeftDown,
+    LeftUp,
+}
+
 #[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct TileId(u32);
 
+/// The top 3 bits of the tileset half are reserved for the `TileFlip` bits so that they line up
+/// with the bits Tiled uses in its gids.
+const TILESET_MASK: u32 = 0x1FFF;
+
 impl TileId {
     pub fn new(tileset: TilesetId, tile: LocalTileId) -> Self {
-        TileId((tileset.0 as u32) << 16 | tile.0 as u32)
+        TileId((tileset.0 as u32 & TILESET_MASK) << 16 | tile.0 as u32)
+    }
+
+    pub fn with_flip(self, flip: TileFlip) -> Self {
+        TileId(self.0 & !TileFlip::ALL.0 | flip.0 & TileFlip::ALL.0)
     }
 
     pub fn tileset(self) -> TilesetId {
-        TilesetId((self.0 >> 16) as u16)
+        TilesetId(((self.0 >> 16) & TILESET_MASK) as u16)
     }
 
     pub fn tile(self) -> LocalTileId {
         LocalTileId(self.0 as u16)
     }
+
+    pub fn flip(self) -> TileFlip {
+        TileFlip(self.0 & TileFlip::ALL.0)
+    }
 }
 
 impl std::fmt::Debug for TileId {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        fmt.debug_tuple("TileId")
-            .field(&self.tileset().0)
-            .field(&self.tile().0)
-            .finish()
+        let mut dbg = fmt.debug_tuple("TileId");
+        dbg.field(&self.tileset().0).field(&self.tile().0);
+        if self.flip() != TileFlip::NONE {
+            dbg.field(&self.flip());
+        }
+        dbg.finish()
+    }
+}
+
+#[auto_enum::enum_flags(u32)]
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub enum TileFlip {
+    DIAGONAL = 0x2000_0000,
+    VERTICAL = 0x4000_0000,
+    HORIZONTAL = 0x8000_0000,
+
+    NONE = 0,
+    ALL = DIAGONAL | VERTICAL | HORIZONTAL,
+}
+
+impl TileFlip {
+    /// The flip bits packed down into the low 3 bits for the tile_chunk shader
+    /// (`1` = diagonal, `2` = vertical, `4` = horizontal)
+    pub fn shader_bits(self) -> u8 {
+        (self.0 >> 29) as u8
+    }
+
+    /// Transforms tile-local coordinates (x right, y down into the negatives, with the tile
+    /// occupying `[0, size.x] x [-size.y, 0]`) the same way Tiled flips the tile image. The
+    /// diagonal flip is applied first, followed by the horizontal and vertical flips.
+    pub fn matrix(self, size: Vector2f) -> Matrix3x2f {
+        let mut mat = Matrix3x2f::IDENTITY;
+        let mut size = size;
+        if self.is_set(TileFlip::DIAGONAL) {
+            mat = mat * Matrix3x2f::from_tuple((0.0, -1.0, -1.0, 0.0, 0.0, 0.0));
+            size = [size.y, size.x].into();
+        }
+        if self.is_set(TileFlip::HORIZONTAL) {
+            mat = mat * Matrix3x2f::from_tuple((-1.0, 0.0, 0.0, 1.0, size.x, 0.0));
+        }
+        if self.is_set(TileFlip::VERTICAL) {
+            mat = mat * Matrix3x2f::from_tuple((1.0, 0.0, 0.0, -1.0, 0.0, -size.y));
+        }
+        mat
     }
 }
 
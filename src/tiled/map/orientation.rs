@@ -0,0 +1,159 @@
+use crate::tiled::raw;
+
+use failure::{err_msg, Fallible};
+use math2d::{Point2f, Point2i};
+
+/// How tile coordinates are laid out in the world. World units are always one tile width
+/// horizontally and one tile height vertically, so isometric diamonds are 1x1 world units and
+/// neighbouring diamonds are half a unit apart.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    Orthogonal,
+    Isometric,
+    /// Diamond tiles where every other row (or column) is shifted by half a tile, so the map
+    /// stays roughly rectangular
+    Staggered {
+        axis: StaggerAxis,
+        index: StaggerIndex,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StaggerAxis {
+    X,
+    Y,
+}
+
+/// Whether the odd or the even rows (or columns) are the shifted ones
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StaggerIndex {
+    Odd,
+    Even,
+}
+
+impl Orientation {
+    pub fn from_raw(raw: &raw::Map) -> Fallible<Orientation> {
+        Ok(match raw.orientation {
+            raw::map::Orientation::Orthogonal => Orientation::Orthogonal,
+            raw::map::Orientation::Isometric => Orientation::Isometric,
+            raw::map::Orientation::Staggered => {
+                // Tiled's defaults when the attributes are missing
+                let axis = match raw.staggeraxis.unwrap_or(raw::map::Axis::Y) {
+                    raw::map::Axis::X => StaggerAxis::X,
+                    raw::map::Axis::Y => StaggerAxis::Y,
+                };
+                let index = match raw.staggerindex.unwrap_or(raw::map::StaggerIndex::Odd) {
+                    raw::map::StaggerIndex::Odd => StaggerIndex::Odd,
+                    raw::map::StaggerIndex::Even => StaggerIndex::Even,
+                };
+                Orientation::Staggered { axis, index }
+            }
+            raw::map::Orientation::Hexagonal => {
+                return Err(err_msg(
+                    "Hexagonal maps are not supported, use an orthogonal, isometric or \
+                     staggered map instead",
+                ));
+            }
+        })
+    }
+
+    /// The world position of the center of the tile at `tile` (y pointing down)
+    pub fn tile_to_world(self, tile: Point2i) -> Point2f {
+        let (x, y) = (tile.x as f32, tile.y as f32);
+        match self {
+            Orientation::Orthogonal => (x, -y).into(),
+            Orientation::Isometric => ((x - y) * 0.5, -(x + y) * 0.5).into(),
+            Orientation::Staggered { axis, index } => {
+                let along = match axis {
+                    StaggerAxis::X => tile.x,
+                    StaggerAxis::Y => tile.y,
+                };
+                let shift = if index.is_shifted(along) { 0.5 } else { 0.0 };
+                match axis {
+                    StaggerAxis::X => (x * 0.5, -y - shift).into(),
+                    StaggerAxis::Y => (x + shift, -y * 0.5).into(),
+                }
+            }
+        }
+    }
+
+    /// The tile whose cell contains `world`. For diamond tiles that is the diamond `world`
+    /// falls in, not its bounding box.
+    pub fn world_to_tile(self, world: Point2f) -> Point2i {
+        match self {
+            Orientation::Orthogonal => (world.x.round() as i32, (-world.y).round() as i32).into(),
+            Orientation::Isometric => {
+                let x = world.x - world.y;
+                let y = -world.x - world.y;
+                (x.round() as i32, y.round() as i32).into()
+            }
+            Orientation::Staggered { axis, index } => {
+                // The point lies in one of the two rows (or columns) of diamonds it overlaps.
+                // Diamonds are all the same size, so the one it falls in is the one whose center
+                // is closest in L1 distance.
+                let candidates = match axis {
+                    StaggerAxis::X => {
+                        let col = (world.x * 2.0).floor() as i32;
+                        [col, col + 1]
+                    }
+                    StaggerAxis::Y => {
+                        let row = (-world.y * 2.0).floor() as i32;
+                        [row, row + 1]
+                    }
+                };
+                let mut best = (std::f32::INFINITY, Point2i::new(0, 0));
+                for &along in candidates.iter() {
+                    let shift = if index.is_shifted(along) { 0.5 } else { 0.0 };
+                    let across = match axis {
+                        StaggerAxis::X => (-world.y - shift).round() as i32,
+                        StaggerAxis::Y => (world.x - shift).round() as i32,
+                    };
+                    let tile = Self::staggered_tile(axis, along, across);
+                    let center = self.tile_to_world(tile);
+                    let dist = (world.x - center.x).abs() + (world.y - center.y).abs();
+                    if dist < best.0 {
+                        best = (dist, tile);
+                    }
+                }
+                best.1
+            }
+        }
+    }
+
+    /// The values of the `orientation`, `stagger_axis` and `stagger_index` uniforms of the
+    /// tile_chunk shader
+    pub fn shader_uniforms(self) -> (i32, i32, i32) {
+        match self {
+            Orientation::Orthogonal => (0, 0, 0),
+            Orientation::Isometric => (1, 0, 0),
+            Orientation::Staggered { axis, index } => {
+                let axis = match axis {
+                    StaggerAxis::X => 0,
+                    StaggerAxis::Y => 1,
+                };
+                let index = match index {
+                    StaggerIndex::Even => 0,
+                    StaggerIndex::Odd => 1,
+                };
+                (2, axis, index)
+            }
+        }
+    }
+
+    fn staggered_tile(axis: StaggerAxis, along: i32, across: i32) -> Point2i {
+        match axis {
+            StaggerAxis::X => (along, across).into(),
+            StaggerAxis::Y => (across, along).into(),
+        }
+    }
+}
+
+impl StaggerIndex {
+    fn is_shifted(self, along: i32) -> bool {
+        let odd = along.rem_euclid(2) == 1;
+        match self {
+            StaggerIndex::Odd => odd,
+            StaggerIndex::Even => !odd,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::tiled::raw;
+
+use std::collections::HashMap;
+
+use failure::Fallible;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Properties {
+    pub properties: HashMap<String, Property>,
+}
+
+impl Properties {
+    pub fn from_raw(raw: &raw::Properties) -> Properties {
+        let properties = raw
+            .properties
+            .iter()
+            .map(|(name, prop)| (name.clone(), Property::from_raw(prop)))
+            .collect();
+
+        Properties { properties }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        self.properties.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+
+    /// String and file properties
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Property::String(value) | Property::File(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Property::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Float properties, or int properties converted to a float
+    pub fn get_float(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            Property::Float(value) => Some(*value),
+            Property::Int(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            Property::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_color(&self, name: &str) -> Option<math2d::Color> {
+        match self.get(name)? {
+            Property::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The id of the referenced object. Unset object properties return None.
+    pub fn get_object(&self, name: &str) -> Option<i32> {
+        match self.get(name)? {
+            Property::Object(0) => None,
+            Property::Object(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_class(&self, name: &str) -> Option<&Properties> {
+        match self.get(name)? {
+            Property::Class { properties, .. } => Some(properties),
+            _ => None,
+        }
+    }
+
+    /// Deserializes the properties into `T`, with each property becoming a field. Class
+    /// properties become nested structs and object references become their object id.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Fallible<T> {
+        Ok(serde_json::from_value(self.to_json())?)
+    }
+
+    fn to_json(&self) -> Value {
+        let map = self
+            .properties
+            .iter()
+            .map(|(name, prop)| (name.clone(), prop.to_json()))
+            .collect();
+        Value::Object(map)
+    }
+}
+
+impl std::fmt::Debug for Properties {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.properties, fmt)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Property {
+    String(String),
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    Color(math2d::Color),
+    File(String),
+    /// Id of the referenced object, 0 if unset
+    Object(i32),
+    Class {
+        propertytype: String,
+        properties: Properties,
+    },
+}
+
+impl Property {
+    pub fn from_raw(raw: &raw::Property) -> Property {
+        match raw {
+            raw::Property::String(value) => Property::String(value.clone()),
+            raw::Property::Int(value) => Property::Int(*value),
+            raw::Property::Float(value) => Property::Float(*value),
+            raw::Property::Bool(value) => Property::Bool(*value),
+            raw::Property::Color(value) => Property::Color(*value),
+            raw::Property::File(value) => Property::File(value.clone()),
+            raw::Property::Object(value) => Property::Object(*value),
+            raw::Property::Class {
+                propertytype,
+                properties,
+            } => Property::Class {
+                propertytype: propertytype.clone(),
+                properties: Properties::from_raw(properties),
+            },
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Property::String(value) | Property::File(value) => Value::String(value.clone()),
+            Property::Int(value) => (*value).into(),
+            Property::Float(value) => (*value as f64).into(),
+            Property::Bool(value) => (*value).into(),
+            Property::Color(value) => serde_json::to_value(value).unwrap_or(Value::Null),
+            Property::Object(value) => (*value).into(),
+            Property::Class { properties, .. } => properties.to_json(),
+        }
+    }
+}
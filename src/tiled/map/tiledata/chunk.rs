@@ -3,8 +3,9 @@ use crate::graphics::core::GraphicsCore;
 use crate::graphics::GraphicsState;
 use crate::physics::MetaBody;
 use crate::tiled::map::tilesets::Tilesets;
-use crate::tiled::map::TileId;
 use crate::tiled::map::TilesetId;
+use crate::tiled::map::{Orientation, TileFlip, TileId};
+use crate::tiled::tileset::TilesetGpu;
 
 use std::collections::HashSet;
 
@@ -12,40 +13,83 @@ use failure::Fallible;
 use glium::implement_vertex;
 use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
 use glium::VertexBuffer;
-use math2d::Point2f;
+use math2d::{Point2i, Vector2f};
 
 #[derive(Serialize, Deserialize)]
 pub struct Chunk {
     pub used_tilesets: Box<[TilesetId]>,
     pub data: Box<[TileId]>,
 
-    #[serde(skip)] // Don't serialize this field
-    buffers: Option<Box<[VertexBuffer<TileInstance>]>>,
+    /// Bumped whenever the tiles change, so buffers built from older tiles can be told apart
     #[serde(skip)]
-    physics_body: Option<wrapped2d::b2::BodyHandle>,
+    revision: u32,
+    #[serde(skip)]
+    physics_body: Option<BodyId>,
 }
 
+/// The instance buffers a chunk is drawn with, one per used tileset, see `Chunk::create_buffers`
+pub type ChunkBuffers = Box<[VertexBuffer<TileInstance>]>;
+
+/// A body handle is only an index into the physics world, so unlike the body itself it can be
+/// sent to other threads. That keeps chunks, and so whole maps, loadable on worker threads.
+#[derive(Copy, Clone)]
+struct BodyId(wrapped2d::b2::BodyHandle);
+
+unsafe impl Send for BodyId {}
+
 impl Chunk {
     pub fn new(data: impl Into<Box<[TileId]>>) -> Self {
         let data = data.into();
-
-        let used_tilesets = data
-            .iter()
-            .map(|t| t.tileset())
-            .filter(|t| t.0 != 0)
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>()
-            .into_boxed_slice();
+        let used_tilesets = Self::find_used_tilesets(&data);
 
         Chunk {
             data,
             used_tilesets,
-            buffers: None,
+            revision: 0,
             physics_body: None,
         }
     }
 
+    /// Replaces the tile at `index`. Buffers from `create_buffers` are out of date afterwards,
+    /// see `revision`, and existing physics fixtures are left alone, see `rebuild_physics`.
+    pub fn set(&mut self, index: usize, tile: TileId) {
+        self.set_many(Some((index, tile)));
+    }
+
+    /// Like `set` for several tiles, by index, only working out which tilesets are used once at
+    /// the end. Returns whether any tile changed.
+    pub fn set_many(&mut self, tiles: impl IntoIterator<Item = (usize, TileId)>) -> bool {
+        let mut changed = false;
+        for (index, tile) in tiles {
+            if self.data[index] != tile {
+                self.data[index] = tile;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.used_tilesets = Self::find_used_tilesets(&self.data);
+            self.revision = self.revision.wrapping_add(1);
+        }
+        changed
+    }
+
+    /// Changes every time the tiles do, so callers holding on to buffers from `create_buffers`
+    /// know when to rebuild them
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    fn find_used_tilesets(data: &[TileId]) -> Box<[TilesetId]> {
+        data.iter()
+            .map(|t| t.tileset())
+            .filter(|t| t.0 != 0)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+    }
+
     pub fn validate(&self, sets: &Tilesets) -> Fallible<()> {
         use failure::err_msg;
         if self.data.len() != (CHUNK_SIZE * CHUNK_SIZE) as usize {
@@ -71,13 +115,8 @@ impl Chunk {
         Ok(())
     }
 
-    /// Ensure the buffers are initialized. This is safe to call every frame, it's a simple
-    /// is_some() check to instant return.
-    pub fn initialize(&mut self, core: &GraphicsCore) -> Fallible<()> {
-        if self.buffers.is_some() {
-            return Ok(());
-        }
-
+    /// Builds the buffers `render` draws the chunk's current tiles with
+    pub fn create_buffers(&self, core: &GraphicsCore) -> Fallible<ChunkBuffers> {
         let mut buffers = Vec::with_capacity(self.used_tilesets.len());
         let mut data = Vec::with_capacity(self.data.len());
 
@@ -90,6 +129,7 @@ impl Chunk {
                     data.push(TileInstance {
                         tile_pos: [x, y],
                         tile_id: tile.tile().0,
+                        tile_flip: tile.flip().shader_bits(),
                     });
                 }
             }
@@ -98,56 +138,122 @@ impl Chunk {
             data.clear();
         }
 
-        self.buffers = Some(buffers.into_boxed_slice());
-
-        Ok(())
+        Ok(buffers.into_boxed_slice())
     }
 
+    /// Creates the static body for the chunk at chunk position `cpos` on a layer offset by
+    /// `offset`
     pub fn create_physics(
         &mut self,
         sets: &Tilesets,
-        pos: &Point2f,
+        orientation: Orientation,
+        cpos: Point2i,
+        offset: Vector2f,
         physics: &mut crate::physics::World,
     ) {
         use wrapped2d::b2;
 
+        let origin = Self::origin_tile(cpos);
+        let pos = orientation.tile_to_world(origin) + offset;
+
         let mut def = b2::BodyDef::new();
         def.body_type = b2::BodyType::Static;
         def.position = [pos.x, pos.y].into();
 
         let body = physics.create_body(&def);
-        self.physics_body = Some(body);
+        self.physics_body = Some(BodyId(body));
+
+        self.create_fixtures(sets, orientation, origin, &mut physics.body_mut(body));
+    }
+
+    /// Replaces the chunk's body with one matching its current tiles, after they were changed
+    /// with `set` or `set_many`
+    pub fn rebuild_physics(
+        &mut self,
+        sets: &Tilesets,
+        orientation: Orientation,
+        cpos: Point2i,
+        offset: Vector2f,
+        physics: &mut crate::physics::World,
+    ) {
+        if let Some(BodyId(body)) = self.physics_body.take() {
+            physics.destroy_body(body);
+        }
+        self.create_physics(sets, orientation, cpos, offset, physics);
+    }
 
-        self.create_fixtures(sets, &mut physics.body_mut(body));
+    /// The tile position of the chunk's top-left tile
+    fn origin_tile(cpos: Point2i) -> Point2i {
+        (cpos.x * CHUNK_SIZE, cpos.y * CHUNK_SIZE).into()
     }
 
-    fn create_fixtures(&self, sets: &Tilesets, body: &mut MetaBody) {
+    fn create_fixtures(
+        &self,
+        sets: &Tilesets,
+        orientation: Orientation,
+        origin: Point2i,
+        body: &mut MetaBody,
+    ) {
+        use math2d::Matrix3x2f as M;
+
+        let body_pos = orientation.tile_to_world(origin);
+
         for (i, &tid) in self.data.iter().enumerate() {
-            if let Some(tile) = sets.get_tile(tid) {
-                let x = (i % CHUNK_SIZE as usize) as f32 - 0.5;
-                let y = -((i / CHUNK_SIZE as usize) as f32) + 0.5;
-                tile.create_collider(&(x, y).into(), body);
+            let tileset = match sets.get(tid.tileset()) {
+                Some(tileset) => tileset,
+                None => continue,
+            };
+
+            let tile = tileset.get(tid.tile());
+            let size = tileset.tile_size(tid.tile());
+            if let (Some(tile), Some(size)) = (tile, size) {
+                // Tiles sit on the bottom-left corner of their cell, so find where the top-left
+                // corner of the (possibly oversized and diagonally flipped) tile ends up. For
+                // diamond tiles the cell is the diamond's 1x1 bounding box, like in Tiled.
+                let height = if tid.flip().is_set(TileFlip::DIAGONAL) {
+                    size.x
+                } else {
+                    size.y
+                };
+                let local = (i as i32 % CHUNK_SIZE, i as i32 / CHUNK_SIZE);
+                let tile_pos = (origin.x + local.0, origin.y + local.1).into();
+                let center = orientation.tile_to_world(tile_pos) - body_pos;
+                let x = center.x - 0.5 + tileset.offset.x;
+                let y = center.y - 0.5 + height + tileset.offset.y;
+                let transform = tid.flip().matrix(size) * M::translation((x, y));
+                tile.create_collider(&transform, body);
             }
         }
     }
 
+    /// Draws the chunk at chunk position `cpos` on a layer offset by `offset`. On isometric and
+    /// staggered maps the tiles further down the screen are pushed slightly in front so
+    /// overlapping tiles cover the ones behind them regardless of draw order. `buffers` has to
+    /// come from `create_buffers` since the last change to the tiles.
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
         &self,
         graphics: &mut GraphicsState,
         sets: &Tilesets,
-        position: Point2f,
+        sets_gpu: &[TilesetGpu],
+        buffers: &ChunkBuffers,
+        orientation: Orientation,
+        cpos: Point2i,
+        offset: Vector2f,
         layer: f32,
     ) -> Fallible<()> {
-        let buffers = self.buffers.as_ref().unwrap();
+        let origin = Self::origin_tile(cpos);
+        let (orientation, stagger_axis, stagger_index) = orientation.shader_uniforms();
         let frame = graphics.frame.gameplay_frame().unwrap();
         for (i, &ts_id) in self.used_tilesets.iter().enumerate() {
             use glium::index::{NoIndices, PrimitiveType};
             use glium::{uniform, DrawParameters, Surface};
 
             let tileset = sets.get(ts_id).unwrap();
-            let tile_size = tileset.tile_scale;
-            let tex = tileset.tileset_image();
-            let rect_buffer = tileset.tile_rect_buffer();
+            let tile_offset = tileset.offset;
+            let gpu = &sets_gpu[ts_id.0 as usize - 1];
+            let tex = &gpu.image;
+            let rect_buffer = &gpu.rect_buffer;
             let camera = graphics.camera.buffer();
 
             let buffers = (&graphics.core.quad, buffers[i].per_instance().unwrap());
@@ -165,8 +271,12 @@ impl Chunk {
                         .magnify_filter(MagnifySamplerFilter::Nearest)
                         .anisotropy(8),
                     rect_buffer: rect_buffer,
-                    chunk_pos: [position.x, position.y],
-                    tile_size: [tile_size.x, tile_size.y],
+                    chunk_tile: [origin.x, origin.y],
+                    layer_offset: [offset.x, offset.y],
+                    tile_offset: [tile_offset.x, tile_offset.y],
+                    orientation: orientation,
+                    stagger_axis: stagger_axis,
+                    stagger_index: stagger_index,
                     layer: layer,
                 },
                 &DrawParameters {
@@ -189,6 +299,7 @@ impl Chunk {
 pub struct TileInstance {
     pub tile_pos: [u8; 2],
     pub tile_id: u16,
+    pub tile_flip: u8,
 }
 
-implement_vertex!(TileInstance, tile_pos, tile_id);
+implement_vertex!(TileInstance, tile_pos, tile_id, tile_flip);
@@ -1,13 +1,15 @@
 use crate::tiled::map::tiledata::chunk::Chunk;
 use crate::tiled::map::tilesets::Tilesets;
+use crate::tiled::map::{Orientation, TileId};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use failure::Fallible;
-use math2d::{Point2f, Point2i};
+use math2d::{Point2f, Point2i, Recti};
 
 pub mod chunk;
 mod chunk_serialization;
+pub mod terrain;
 
 pub const CHUNK_SIZE: i32 = 16;
 
@@ -27,10 +29,80 @@ impl TileData {
         Ok(())
     }
 
-    pub fn tile_pos_at(&self, world_pos: Point2f) -> Point2i {
-        let x = (world_pos.x).round() as i32;
-        let y = (-world_pos.y).round() as i32;
-        (x, y).into()
+    /// Bounds of the non-empty tiles in tile coordinates (y pointing down). `right` and
+    /// `bottom` are exclusive. None if there are no tiles.
+    pub fn tile_bounds(&self) -> Option<Recti> {
+        let mut bounds: Option<Recti> = None;
+        for (cpos, chunk) in self.chunks.iter() {
+            for (i, &tile) in chunk.data.iter().enumerate() {
+                if tile == TileId::default() {
+                    continue;
+                }
+
+                let x = cpos.x * CHUNK_SIZE + i as i32 % CHUNK_SIZE;
+                let y = cpos.y * CHUNK_SIZE + i as i32 / CHUNK_SIZE;
+                let tile = Recti::new(x, y, x + 1, y + 1);
+                bounds = Some(match bounds {
+                    Some(b) => union(b, tile),
+                    None => tile,
+                });
+            }
+        }
+        bounds
+    }
+
+    pub fn tile_pos_at(&self, orientation: Orientation, world_pos: Point2f) -> Point2i {
+        orientation.world_to_tile(world_pos)
+    }
+
+    pub fn tile_at(&self, pos: Point2i) -> TileId {
+        let cpos = Self::chunk_pos(pos);
+        match self.chunks.get(&cpos) {
+            Some(chunk) => chunk.data[Self::index_in_chunk(pos, cpos)],
+            None => TileId::default(),
+        }
+    }
+
+    /// Places `tile` at `pos`, creating the chunk if needed
+    pub fn set_tile(&mut self, pos: Point2i, tile: TileId) {
+        self.set_tiles(Some((pos, tile)));
+    }
+
+    /// Places every tile, creating chunks as needed. Each touched chunk is only refreshed once,
+    /// however many of its tiles change. Returns the positions of the chunks that changed, e.g.
+    /// to rebuild their physics with `Chunk::rebuild_physics`.
+    pub fn set_tiles(
+        &mut self,
+        tiles: impl IntoIterator<Item = (Point2i, TileId)>,
+    ) -> HashSet<Point2i> {
+        let mut by_chunk = HashMap::<Point2i, Vec<(usize, TileId)>>::new();
+        for (pos, tile) in tiles {
+            let cpos = Self::chunk_pos(pos);
+            let index = Self::index_in_chunk(pos, cpos);
+            by_chunk.entry(cpos).or_default().push((index, tile));
+        }
+
+        let mut changed = HashSet::new();
+        for (cpos, tiles) in by_chunk {
+            let empty = tiles.iter().all(|&(_, tile)| tile == TileId::default());
+            if empty && !self.chunks.contains_key(&cpos) {
+                continue;
+            }
+
+            let chunk = self.chunks.entry(cpos).or_insert_with(|| {
+                Chunk::new(vec![TileId::default(); (CHUNK_SIZE * CHUNK_SIZE) as usize])
+            });
+            if chunk.set_many(tiles) {
+                changed.insert(cpos);
+            }
+        }
+        changed
+    }
+
+    fn index_in_chunk(pos: Point2i, cpos: Point2i) -> usize {
+        let lx = pos.x - cpos.x * CHUNK_SIZE;
+        let ly = pos.y - cpos.y * CHUNK_SIZE;
+        (ly * CHUNK_SIZE + lx) as usize
     }
 
     pub fn chunk_pos(tile_pos: Point2i) -> Point2i {
@@ -39,3 +111,12 @@ impl TileData {
         (x, y).into()
     }
 }
+
+pub(crate) fn union(a: Recti, b: Recti) -> Recti {
+    Recti::new(
+        a.left.min(b.left),
+        a.top.min(b.top),
+        a.right.max(b.right),
+        a.bottom.max(b.bottom),
+    )
+}
@@ -0,0 +1,148 @@
+use crate::tiled::map::tiledata::TileData;
+use crate::tiled::map::tilesets::Tilesets;
+use crate::tiled::map::{TileFlip, TileId, TilesetId};
+use crate::tiled::tileset::wang::WangId;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use failure::{err_msg, Fallible};
+use math2d::{Point2i, Recti};
+use rand::Rng;
+
+/// Terrain colors painted onto the corners and edges of the tile grid, to be turned into tiles
+/// by `TileData::apply_terrain`. Colors are 1-based indices into a `WangSet`'s colors.
+#[derive(Clone, Default, Debug)]
+pub struct TerrainPaint {
+    /// Keyed by the tile the corner is the top-left of
+    corners: HashMap<Point2i, u8>,
+    /// Keyed by the tile the edge is the top of
+    top_edges: HashMap<Point2i, u8>,
+    /// Keyed by the tile the edge is the left of
+    left_edges: HashMap<Point2i, u8>,
+}
+
+impl TerrainPaint {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Paints the corner at the top-left of tile `pos`
+    pub fn set_corner(&mut self, pos: Point2i, color: u8) {
+        self.corners.insert(pos, color);
+    }
+
+    /// Paints the top edge of tile `pos`
+    pub fn set_top_edge(&mut self, pos: Point2i, color: u8) {
+        self.top_edges.insert(pos, color);
+    }
+
+    /// Paints the left edge of tile `pos`
+    pub fn set_left_edge(&mut self, pos: Point2i, color: u8) {
+        self.left_edges.insert(pos, color);
+    }
+
+    /// Paints every corner and edge of tile `pos`
+    pub fn fill_tile(&mut self, pos: Point2i, color: u8) {
+        let (x, y) = (pos.x, pos.y);
+        for &(dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+            self.set_corner((x + dx, y + dy).into(), color);
+        }
+        self.set_top_edge(pos, color);
+        self.set_top_edge((x, y + 1).into(), color);
+        self.set_left_edge(pos, color);
+        self.set_left_edge((x + 1, y).into(), color);
+    }
+
+    /// Paints every tile in `rect` (right and bottom exclusive)
+    pub fn fill_rect(&mut self, rect: Recti, color: u8) {
+        for y in rect.top..rect.bottom {
+            for x in rect.left..rect.right {
+                self.fill_tile((x, y).into(), color);
+            }
+        }
+    }
+
+    /// The painted colors around tile `pos`, with 0 where nothing was painted
+    pub fn wangid_at(&self, pos: Point2i) -> WangId {
+        let (x, y) = (pos.x, pos.y);
+        let get = |map: &HashMap<Point2i, u8>, x, y| map.get(&(x, y).into()).cloned();
+
+        let mut wangid = WangId::default();
+        let ids = [
+            (WangId::TOP, get(&self.top_edges, x, y)),
+            (WangId::TOP_RIGHT, get(&self.corners, x + 1, y)),
+            (WangId::RIGHT, get(&self.left_edges, x + 1, y)),
+            (WangId::BOTTOM_RIGHT, get(&self.corners, x + 1, y + 1)),
+            (WangId::BOTTOM, get(&self.top_edges, x, y + 1)),
+            (WangId::BOTTOM_LEFT, get(&self.corners, x, y + 1)),
+            (WangId::LEFT, get(&self.left_edges, x, y)),
+            (WangId::TOP_LEFT, get(&self.corners, x, y)),
+        ];
+        for &(i, color) in ids.iter() {
+            wangid.0[i] = color.unwrap_or(0);
+        }
+        wangid
+    }
+
+    /// Every tile touching a painted corner or edge, in row order
+    pub fn affected_tiles(&self) -> Vec<Point2i> {
+        let mut tiles = BTreeSet::new();
+        for pos in self.corners.keys() {
+            for &(dx, dy) in [(-1, -1), (0, -1), (-1, 0), (0, 0)].iter() {
+                tiles.insert((pos.y + dy, pos.x + dx));
+            }
+        }
+        for pos in self.top_edges.keys() {
+            tiles.insert((pos.y - 1, pos.x));
+            tiles.insert((pos.y, pos.x));
+        }
+        for pos in self.left_edges.keys() {
+            tiles.insert((pos.y, pos.x - 1));
+            tiles.insert((pos.y, pos.x));
+        }
+        tiles.into_iter().map(|(y, x)| (x, y).into()).collect()
+    }
+}
+
+impl TileData {
+    /// Replaces every tile touched by `paint` with one from wang set `wang_set` of `tileset`
+    /// that matches the painted colors, like Tiled's terrain brush. Corners and edges that
+    /// weren't painted keep the colors of the tile that was already there, so the painted area
+    /// blends into its surroundings.
+    ///
+    /// Returns the positions of the chunks that changed. Their physics isn't touched, so call
+    /// `Chunk::rebuild_physics` on them if the layer has colliders.
+    pub fn apply_terrain(
+        &mut self,
+        sets: &Tilesets,
+        tileset: TilesetId,
+        wang_set: usize,
+        paint: &TerrainPaint,
+        rng: &mut impl Rng,
+    ) -> Fallible<HashSet<Point2i>> {
+        let set = sets
+            .get(tileset)
+            .ok_or_else(|| err_msg("Terrain tileset is not part of the map"))?
+            .wang_sets
+            .get(wang_set)
+            .ok_or_else(|| err_msg("Tileset has no such wang set"))?;
+
+        // Every tile is chosen from what was there before, so the writes can wait until the end
+        let mut tiles = Vec::new();
+        for pos in paint.affected_tiles() {
+            let current = self.tile_at(pos);
+            let existing = if current.tileset() == tileset && current.flip() == TileFlip::NONE {
+                set.wangid(current.tile()).unwrap_or_default()
+            } else {
+                WangId::default()
+            };
+
+            let wanted = paint.wangid_at(pos).or(existing);
+            if let Some(tile) = set.choose(wanted, rng) {
+                tiles.push((pos, TileId::new(tileset, tile)));
+            }
+        }
+
+        Ok(self.set_tiles(tiles))
+    }
+}
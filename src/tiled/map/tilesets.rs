@@ -1,7 +1,7 @@
 use crate::graphics::core::GraphicsCore;
-use crate::tiled::map::{LocalTileId, TileId, TilesetId};
+use crate::tiled::map::{LocalTileId, TileFlip, TileId, TilesetId};
 use crate::tiled::raw;
-use crate::tiled::tileset::{tile::Tile, Tileset};
+use crate::tiled::tileset::{tile::Tile, Tileset, TilesetGpu};
 
 use std::ops::Range;
 
@@ -29,9 +29,42 @@ impl Tilesets {
         Ok(tilesets)
     }
 
-    pub fn initialize(&mut self, core: &GraphicsCore) -> Fallible<()> {
-        for (_, tileset) in self.tilesets.iter_mut() {
-            tileset.initialize(core)?;
+    /// Wraps tilesets that were loaded some other way, paired with their gid ranges
+    pub(crate) fn from_list(tilesets: Vec<(Range<u32>, Tileset)>) -> Fallible<Tilesets> {
+        let tilesets = Tilesets { tilesets };
+        tilesets.validate()?;
+        Ok(tilesets)
+    }
+
+    /// Decodes every tileset image for `upload`. This is the slow part of getting a map ready
+    /// to draw and doesn't need the GPU, so it can run on another thread.
+    pub fn decode_images(&self) -> Fallible<Vec<image::RgbaImage>> {
+        self.tilesets
+            .iter()
+            .map(|(_, tileset)| tileset.image.to_rgba())
+            .collect()
+    }
+
+    /// Uploads every tileset, given their images from `decode_images`. The result is indexed by
+    /// `TilesetId` - 1.
+    pub fn upload(
+        &mut self,
+        core: &GraphicsCore,
+        images: Vec<image::RgbaImage>,
+    ) -> Fallible<Vec<TilesetGpu>> {
+        if images.len() != self.tilesets.len() {
+            return Err(err_msg("Wrong number of tileset images to upload"));
+        }
+        self.tilesets
+            .iter_mut()
+            .zip(images)
+            .map(|((_, tileset), rgba)| tileset.upload(core, rgba))
+            .collect()
+    }
+
+    pub fn update_animations(&mut self, gpu: &[TilesetGpu], time: f64) -> Fallible<()> {
+        for ((_, tileset), gpu) in self.tilesets.iter_mut().zip(gpu) {
+            tileset.update_animations(gpu, time)?;
         }
         Ok(())
     }
@@ -56,19 +89,38 @@ impl Tilesets {
     }
 
     pub fn tile_from_raw(&self, gid: raw::GlobalTileId) -> TileId {
-        let gid = gid.0;
+        let flip = TileFlip(gid.flags() & TileFlip::ALL.0);
+        let gid = gid.id().0;
         for (i, (range, _)) in self.tilesets.iter().enumerate() {
             if gid < range.start {
                 break;
             } else if gid < range.end {
                 let tileset = TilesetId((i + 1) as u16);
                 let tile = LocalTileId((gid - range.start) as u16);
-                return TileId::new(tileset, tile);
+                return TileId::new(tileset, tile).with_flip(flip);
             }
         }
         TileId::default()
     }
 
+    /// The inverse of `tile_from_raw`, using the firstgids the tilesets were loaded with
+    pub fn tile_to_raw(&self, tile: TileId) -> raw::GlobalTileId {
+        let index = (tile.tileset().0 as usize).checked_sub(1);
+        match index.and_then(|i| self.tilesets.get(i)) {
+            Some((range, _)) => {
+                raw::GlobalTileId((range.start + tile.tile().0 as u32) | tile.flip().0)
+            }
+            None => raw::GlobalTileId(0),
+        }
+    }
+
+    /// Every tileset along with its firstgid
+    pub fn iter(&self) -> impl Iterator<Item = (raw::GlobalTileId, &Tileset)> {
+        self.tilesets
+            .iter()
+            .map(|(range, tileset)| (raw::GlobalTileId(range.start), tileset))
+    }
+
     pub fn get(&self, id: TilesetId) -> Option<&Tileset> {
         let id = id.0 as usize;
         if id == 0 {
@@ -2,16 +2,44 @@ use self::source::Source;
 
 use failure::Fallible;
 
+pub mod binmap;
 pub mod map;
 pub mod source;
 pub mod tileset;
+pub mod writer;
 
 pub mod raw;
 
-pub fn load_tmx(source: Source) -> Fallible<map::Map> {
+/// A loaded file along with the problems the parser found in it that weren't serious enough to
+/// fail loading, for the caller to report
+pub struct Parsed<T> {
+    pub data: T,
+    pub warnings: Vec<raw::Diagnostic>,
+}
+
+pub fn load_tmx(source: Source) -> Fallible<Parsed<map::Map>> {
     let raw_map = raw::ParseContext::parse(source, "map", raw::Map::parse_tag)?;
     let map = map::Map::from_raw(&raw_map.data)?;
-    Ok(map)
+    Ok(Parsed {
+        data: map,
+        warnings: raw_map.warnings,
+    })
+}
+
+/// Loads a map saved in Tiled's JSON format. External tilesets and templates may be either
+/// JSON or XML.
+pub fn load_tmj(source: Source) -> Fallible<Parsed<map::Map>> {
+    let raw_map = raw::json::JsonContext::parse(source, raw::json::parse_map)?;
+    let map = map::Map::from_raw(&raw_map.data)?;
+    Ok(Parsed {
+        data: map,
+        warnings: raw_map.warnings,
+    })
+}
+
+/// Writes the map out as TMX for editing in Tiled, with its tilesets and images saved next to it
+pub fn save_tmx(path: impl AsRef<std::path::Path>, map: &map::Map) -> Fallible<()> {
+    writer::save_tmx(path.as_ref(), map)
 }
 
 pub fn save_jsonmap(writer: impl std::io::Write, map: &map::Map) -> Fallible<()> {
@@ -19,11 +47,9 @@ pub fn save_jsonmap(writer: impl std::io::Write, map: &map::Map) -> Fallible<()>
     Ok(())
 }
 
+/// Writes a prebaked map, see `binmap` for the format
 pub fn save_binmap(writer: impl std::io::Write, map: &map::Map) -> Fallible<()> {
-    let compression = flate2::Compression::best();
-    let writer = flate2::write::GzEncoder::new(writer, compression);
-    bincode::serialize_into(writer, map)?;
-    Ok(())
+    binmap::save(writer, map)
 }
 
 pub fn load_jsonmap(reader: impl std::io::Read) -> Fallible<map::Map> {
@@ -31,8 +57,7 @@ pub fn load_jsonmap(reader: impl std::io::Read) -> Fallible<map::Map> {
     Ok(map)
 }
 
+/// Loads a prebaked map, upgrading it if it was written by an older engine
 pub fn load_binmap(reader: impl std::io::Read) -> Fallible<map::Map> {
-    let reader = flate2::read::GzDecoder::new(reader);
-    let map = bincode::deserialize_from(reader)?;
-    Ok(map)
+    binmap::load(reader)
 }
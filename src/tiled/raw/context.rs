@@ -1,3 +1,5 @@
+use crate::tiled::raw::diagnostic::{Diagnostic, Severity};
+use crate::tiled::raw::template::Template;
 use crate::tiled::raw::tileset::Tileset;
 use crate::tiled::source::Source;
 
@@ -6,20 +8,26 @@ use std::sync::Arc;
 
 use failure::{err_msg, Fallible};
 use xml::attribute as xa;
+use xml::common::{Position, TextPosition};
 
 pub struct ParseContext<'a> {
     pub reader: xml::EventReader<&'a [u8]>,
     pub source: Source,
     pub tilesets: &'a mut HashMap<Source, Arc<Tileset>>,
-    pub warnings: &'a mut Vec<String>,
+    pub templates: &'a mut HashMap<Source, Arc<Template>>,
+    pub warnings: &'a mut Vec<Diagnostic>,
     pub config: &'a xml::ParserConfig,
     pub parseorder: i32,
+    /// Names of the elements currently being parsed, outermost first
+    pub path: Vec<String>,
+    pub included_from: Vec<(Source, Option<TextPosition>)>,
 }
 
 pub struct ParseResult<T> {
     pub data: T,
     pub tilesets: HashMap<Source, Arc<Tileset>>,
-    pub warnings: Vec<String>,
+    pub templates: HashMap<Source, Arc<Template>>,
+    pub warnings: Vec<Diagnostic>,
 }
 
 impl<'a> ParseContext<'a> {
@@ -30,6 +38,7 @@ impl<'a> ParseContext<'a> {
     ) -> Fallible<ParseResult<R>> {
         let data = source.read_all()?;
         let mut tilesets = HashMap::new();
+        let mut templates = HashMap::new();
         let mut warnings = Vec::new();
         let mut config = xml::ParserConfig::default();
         config.whitespace_to_characters = true;
@@ -37,12 +46,15 @@ impl<'a> ParseContext<'a> {
 
         let data = {
             let mut ctx = ParseContext {
-                reader: xml::EventReader::new(&data),
+                reader: xml::EventReader::new_with_config(&data[..], config.clone()),
                 source: source,
                 tilesets: &mut tilesets,
+                templates: &mut templates,
                 warnings: &mut warnings,
                 config: &config,
                 parseorder: 0,
+                path: Vec::new(),
+                included_from: Vec::new(),
             };
             ctx.parse_root(root_tag, func)?
         };
@@ -50,6 +62,7 @@ impl<'a> ParseContext<'a> {
         Ok(ParseResult {
             data,
             tilesets,
+            templates,
             warnings,
         })
     }
@@ -58,6 +71,15 @@ impl<'a> ParseContext<'a> {
         &mut self,
         root_tag: &str,
         func: impl FnOnce(&mut ParseContext, &[xa::OwnedAttribute]) -> Fallible<R>,
+    ) -> Fallible<R> {
+        self.parse_root_inner(root_tag, func)
+            .map_err(|err| self.diagnostic_from(err).into())
+    }
+
+    fn parse_root_inner<R>(
+        &mut self,
+        root_tag: &str,
+        func: impl FnOnce(&mut ParseContext, &[xa::OwnedAttribute]) -> Fallible<R>,
     ) -> Fallible<R> {
         loop {
             use xml::reader::XmlEvent;
@@ -80,20 +102,45 @@ impl<'a> ParseContext<'a> {
         root_tag: &str,
         func: impl FnOnce(&mut ParseContext, &[xa::OwnedAttribute]) -> Fallible<R>,
     ) -> Fallible<R> {
+        let mut included_from = vec![(self.source.clone(), Some(self.reader.position()))];
+        included_from.extend(self.included_from.iter().cloned());
+
         let mut ctx = ParseContext {
             reader: xml::EventReader::new_with_config(data, self.config.clone()),
             source,
             tilesets: &mut *self.tilesets,
+            templates: &mut *self.templates,
             warnings: &mut *self.warnings,
             config: self.config,
             parseorder: 0,
+            path: Vec::new(),
+            included_from,
         };
 
         ctx.parse_root(root_tag, func)
     }
 
     pub fn warning(&mut self, msg: impl Into<String>) {
-        self.warnings.push(msg.into());
+        let diag = Diagnostic {
+            severity: Severity::Warning,
+            source: self.source.clone(),
+            position: Some(self.reader.position()),
+            path: self.path.clone(),
+            included_from: self.included_from.clone(),
+            message: msg.into(),
+        };
+        self.warnings.push(diag);
+    }
+
+    /// Points an error at the element the reader stopped on
+    pub fn diagnostic_from(&self, err: failure::Error) -> Diagnostic {
+        Diagnostic::from_error(
+            err,
+            &self.source,
+            Some(self.reader.position()),
+            &self.path,
+            &self.included_from,
+        )
     }
 
     pub fn parseorder(&mut self) -> ParseOrder {
@@ -103,4 +150,4 @@ impl<'a> ParseContext<'a> {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ParseOrder(i32);
+pub struct ParseOrder(pub(super) i32);
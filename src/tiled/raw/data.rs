@@ -6,7 +6,8 @@ use xml::attribute as xa;
 
 #[derive(Debug)]
 pub enum Data {
-    Plain(Vec<Vec<GlobalTileId>>),
+    /// Row-major tiles covering the whole layer
+    Plain(Vec<GlobalTileId>),
     Chunked(Vec<Chunk>),
 }
 
@@ -31,14 +32,8 @@ impl Data {
         if !chunk.is_empty() {
             Ok(Data::Chunked(chunk))
         } else {
-            // CSV
-            let data: Fallible<_> = content
-                .split_whitespace()
-                .filter(|s| !s.is_empty())
-                .map(|s| s.split(',').map(|s| Ok(GlobalTileId(s.trim().parse()?))).collect())
-                .collect();
-
-            Ok(Data::Plain(data?))
+            let data = decode_tiles(&content, is_base64, compression)?;
+            Ok(Data::Plain(data))
         }
     }
 }
@@ -66,53 +61,7 @@ impl Chunk {
             </chunk>
         }
 
-        let data = if is_base64 {
-            use byteorder::{ByteOrder, LE};
-            let raw_data = base64::decode(content.trim())?;
-            let byte_data = match compression {
-                None => raw_data,
-
-                Some("gzip") => {
-                    use flate2::bufread::GzDecoder;
-                    use std::io::Read;
-                    let mut decoder = GzDecoder::new(raw_data.as_slice());
-                    let mut data = Vec::new();
-                    decoder.read_to_end(&mut data)?;
-                    data
-                }
-
-                Some("zlib") => {
-                    use flate2::bufread::ZlibDecoder;
-                    use std::io::Read;
-                    let mut decoder = ZlibDecoder::new(raw_data.as_slice());
-                    let mut data = Vec::new();
-                    decoder.read_to_end(&mut data)?;
-                    data
-                }
-
-                Some(fmt) => {
-                    return Err(failure::err_msg(format!(
-                        "Unknown tile compression format '{}'",
-                        fmt
-                    )));
-                }
-            };
-
-            byte_data
-                .chunks(4)
-                .map(LE::read_u32)
-                .map(GlobalTileId)
-                .collect()
-        } else {
-            let data: Fallible<_> = content
-                .split_whitespace()
-                .filter(|s| !s.is_empty())
-                .flat_map(|s| s.split(','))
-                .map(|s| Ok(GlobalTileId(s.parse()?)))
-                .collect();
-
-            data?
-        };
+        let data = decode_tiles(&content, is_base64, compression)?;
 
         Ok(Chunk {
             x,
@@ -123,3 +72,67 @@ impl Chunk {
         })
     }
 }
+
+pub(super) fn decode_tiles(
+    content: &str,
+    is_base64: bool,
+    compression: Option<&str>,
+) -> Fallible<Vec<GlobalTileId>> {
+    // Empty layers of infinite maps have no content at all, not even an empty compressed stream
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if is_base64 {
+        use byteorder::{ByteOrder, LE};
+        let raw_data = base64::decode(content.trim())?;
+        let byte_data = match compression {
+            None => raw_data,
+
+            Some("gzip") => {
+                use flate2::bufread::GzDecoder;
+                use std::io::Read;
+                let mut decoder = GzDecoder::new(raw_data.as_slice());
+                let mut data = Vec::new();
+                decoder.read_to_end(&mut data)?;
+                data
+            }
+
+            Some("zlib") => {
+                use flate2::bufread::ZlibDecoder;
+                use std::io::Read;
+                let mut decoder = ZlibDecoder::new(raw_data.as_slice());
+                let mut data = Vec::new();
+                decoder.read_to_end(&mut data)?;
+                data
+            }
+
+            Some("zstd") => zstd::stream::decode_all(raw_data.as_slice())?,
+
+            Some(fmt) => {
+                return Err(failure::err_msg(format!(
+                    "Unknown tile compression format '{}'",
+                    fmt
+                )));
+            }
+        };
+
+        if byte_data.len() % 4 != 0 {
+            return Err(failure::err_msg("Tile data is not a whole number of gids"));
+        }
+
+        Ok(byte_data
+            .chunks(4)
+            .map(LE::read_u32)
+            .map(GlobalTileId)
+            .collect())
+    } else {
+        // CSV rows end in a trailing comma, so skip the empty entries
+        content
+            .split_whitespace()
+            .flat_map(|s| s.split(','))
+            .filter(|s| !s.is_empty())
+            .map(|s| Ok(GlobalTileId(s.parse()?)))
+            .collect()
+    }
+}
@@ -0,0 +1,88 @@
+use crate::tiled::source::Source;
+
+use std::fmt;
+
+use xml::common::TextPosition;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Something the parser found while reading a Tiled file, pointing at where it happened
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub source: Source,
+    /// Position in `source`. Not available for JSON files.
+    pub position: Option<TextPosition>,
+    /// The elements enclosing the problem, outermost first (e.g. `["tileset", "tile"]`)
+    pub path: Vec<String>,
+    /// The files that referenced `source`, innermost first, and where they referenced it
+    pub included_from: Vec<(Source, Option<TextPosition>)>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Turns an error that stopped the parser into a diagnostic. Errors that are already
+    /// diagnostics (e.g. from a nested tileset file) are passed through unchanged so they keep
+    /// pointing at the file they came from.
+    pub fn from_error(
+        err: failure::Error,
+        source: &Source,
+        position: Option<TextPosition>,
+        path: &[String],
+        included_from: &[(Source, Option<TextPosition>)],
+    ) -> Diagnostic {
+        if let Some(diag) = err
+            .iter_chain()
+            .filter_map(|fail| fail.downcast_ref::<Diagnostic>())
+            .next()
+        {
+            return diag.clone();
+        }
+
+        let message = err
+            .iter_chain()
+            .map(|fail| fail.to_string())
+            .collect::<Vec<_>>()
+            .join(": ");
+
+        Diagnostic {
+            severity: Severity::Error,
+            source: source.clone(),
+            position,
+            path: path.to_vec(),
+            included_from: included_from.to_vec(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+
+        write!(fmt, "{}", self.source)?;
+        if let Some(pos) = self.position {
+            write!(fmt, ":{}", pos)?;
+        }
+        write!(fmt, ": {}: {}", severity, self.message)?;
+        if !self.path.is_empty() {
+            write!(fmt, " (in <{}>)", self.path.join("/"))?;
+        }
+        for (source, pos) in self.included_from.iter() {
+            write!(fmt, "\n    included from {}", source)?;
+            if let Some(pos) = pos {
+                write!(fmt, ":{}", pos)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
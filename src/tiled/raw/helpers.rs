@@ -22,6 +22,8 @@ macro_rules! parse_tag {
             <$open $($val=$valname($valkind))* $(?$oval=$ovalname($ovalkind))*>
         );
 
+        // Only popped on success, so errors still know which element they came from
+        $context.path.push(stringify!($open).to_string());
         loop {
             use xml::reader::XmlEvent;
             match $context.reader.next()? {
@@ -50,7 +52,10 @@ macro_rules! parse_tag {
                 }
                 XmlEvent::EndElement { ref name } => {
                     match name.local_name.as_str() {
-                        stringify!($close) => break,
+                        stringify!($close) => {
+                            $context.path.pop();
+                            break;
+                        }
                         _ => return Err(failure::err_msg(
                             format!("unexpected close tag </{}> (expected </{}>)", name, stringify!($close))
                         ))
@@ -86,24 +91,24 @@ macro_rules! parse_tag {
         for attr in $attrs.iter() {
             match attr.name.local_name.as_str() {
                 $(
-                    stringify!($val) => parse_tag!(@getattr attr $valname $val $valkind),
+                    $valname => parse_tag!(@getattr attr $open $valname $val $valkind),
                 )*
                 $(
-                    stringify!($oval) => parse_tag!(@getattr attr $ovalname $oval $ovalkind),
+                    $ovalname => parse_tag!(@getattr attr $open $ovalname $oval $ovalkind),
                 )*
                 _ => (),
             }
         }
 
         $( let $val: $valkind = $val.ok_or_else(|| {
-            failure::err_msg(concat!("missing attribute ", stringify!($open), ".", stringify!($val)))
+            failure::err_msg(concat!("missing attribute ", stringify!($open), ".", $valname))
         })?; )*
     };
     (@expectclose $context:ident $close:ident) => {{
         use $crate::tiled::raw::helpers::XmlParseHelp;
         $context.reader.expect_close_tag(stringify!($close))?;
     }};
-    (@getattr $attr:ident $valname:literal $val:ident $valkind:ty) => {{
+    (@getattr $attr:ident $open:ident $valname:literal $val:ident $valkind:ty) => {{
         use failure::ResultExt;
         let parse = $attr.value.parse::<$valkind>();
         let res = parse.context(concat!(stringify!($open), ".", $valname, " parse failed"));
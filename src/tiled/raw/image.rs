@@ -20,7 +20,7 @@ impl Image {
                 source = "source"(String)
                 width = "width"(i32)
                 height = "height"(i32)
-                ?transparent = "transparent"(TransColor)
+                ?transparent = "trans"(TransColor)
             />
         }
 
@@ -47,9 +47,11 @@ impl std::str::FromStr for TransColor {
             return Err(err_msg("invalid image.trans color"));
         }
 
+        let hex = |s: &str| u8::from_str_radix(s, 16);
         let (r, g, b) = match s.len() {
-            3 => (s[0..1].parse()?, s[1..2].parse()?, s[2..3].parse()?),
-            6 => (s[0..2].parse()?, s[2..4].parse()?, s[4..6].parse()?),
+            // Short form repeats each digit, so `f0f` is `ff00ff`
+            3 => (hex(&s[0..1])? * 17, hex(&s[1..2])? * 17, hex(&s[2..3])? * 17),
+            6 => (hex(&s[0..2])?, hex(&s[2..4])?, hex(&s[4..6])?),
             _ => return Err(err_msg("invalid image.trans color")),
         };
 
@@ -0,0 +1,1001 @@
+//! Loads Tiled's JSON formats (.tmj maps, .tsj tilesets and .tj templates) into the same raw
+//! structures the XML parser produces.
+
+use crate::tiled::raw::context::{ParseContext, ParseOrder, ParseResult};
+use crate::tiled::raw::data::{decode_tiles, Chunk, Data};
+use crate::tiled::raw::diagnostic::{Diagnostic, Severity};
+use crate::tiled::raw::image::{Image, TransColor};
+use crate::tiled::raw::layer::{group::GroupLayer, image::ImageLayer, tile::TileLayer, Layer};
+use crate::tiled::raw::map::{resolve_template_gids, Axis, Map, Orientation, StaggerIndex};
+use crate::tiled::raw::objects::shape::Shape;
+use crate::tiled::raw::objects::text::{HAlign, Text, VAlign};
+use crate::tiled::raw::objects::{Object, ObjectGroup};
+use crate::tiled::raw::properties::{Properties, Property};
+use crate::tiled::raw::template::Template;
+use crate::tiled::raw::tileset::animation::{Animation, Frame};
+use crate::tiled::raw::tileset::offset::TileOffset;
+use crate::tiled::raw::tileset::terrain::Terrain;
+use crate::tiled::raw::tileset::tile::Tile;
+use crate::tiled::raw::tileset::wang::{
+    merge_legacy_colors, WangColor, WangSet, WangTile, WangType,
+};
+use crate::tiled::raw::tileset::{MapTileset, Tileset};
+use crate::tiled::raw::{DrawOrder, GlobalTileId, LocalTileId, RenderOrder};
+use crate::tiled::source::Source;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible, ResultExt};
+use math2d::Color;
+use serde_json::Value;
+
+pub struct JsonContext<'a> {
+    pub source: Source,
+    pub tilesets: &'a mut HashMap<Source, Arc<Tileset>>,
+    pub templates: &'a mut HashMap<Source, Arc<Template>>,
+    pub warnings: &'a mut Vec<Diagnostic>,
+    pub parseorder: i32,
+    pub included_from: Vec<(Source, Option<xml::common::TextPosition>)>,
+}
+
+impl<'a> JsonContext<'a> {
+    pub fn parse<R>(
+        source: Source,
+        func: impl FnOnce(&mut JsonContext, Obj) -> Fallible<R>,
+    ) -> Fallible<ParseResult<R>> {
+        let mut tilesets = HashMap::new();
+        let mut templates = HashMap::new();
+        let mut warnings = Vec::new();
+
+        let data = {
+            let mut ctx = JsonContext {
+                source,
+                tilesets: &mut tilesets,
+                templates: &mut templates,
+                warnings: &mut warnings,
+                parseorder: 0,
+                included_from: Vec::new(),
+            };
+            ctx.parse_root(func)?
+        };
+
+        Ok(ParseResult {
+            data,
+            tilesets,
+            templates,
+            warnings,
+        })
+    }
+
+    fn parse_root<R>(
+        &mut self,
+        func: impl FnOnce(&mut JsonContext, Obj) -> Fallible<R>,
+    ) -> Fallible<R> {
+        let result = self.source.read_all().and_then(|data| {
+            let value: Value = serde_json::from_slice(&data)
+                .with_context(|_| format!("bad tiled json file {}", self.source))?;
+            let root = Obj::new(&value, "root")?;
+            func(self, root)
+        });
+
+        result.map_err(|err| {
+            Diagnostic::from_error(err, &self.source, None, &[], &self.included_from).into()
+        })
+    }
+
+    fn subcontext<R>(
+        &mut self,
+        source: Source,
+        func: impl FnOnce(&mut JsonContext, Obj) -> Fallible<R>,
+    ) -> Fallible<R> {
+        let mut included_from = vec![(self.source.clone(), None)];
+        included_from.extend(self.included_from.iter().cloned());
+
+        let mut ctx = JsonContext {
+            source,
+            tilesets: &mut *self.tilesets,
+            templates: &mut *self.templates,
+            warnings: &mut *self.warnings,
+            parseorder: 0,
+            included_from,
+        };
+        ctx.parse_root(func)
+    }
+
+    /// Runs the XML parser for files referenced from a JSON file that are still in XML form,
+    /// sharing the caches and warnings.
+    fn xml_subcontext<R>(
+        &mut self,
+        source: Source,
+        root_tag: &str,
+        func: impl FnOnce(&mut ParseContext, &[xml::attribute::OwnedAttribute]) -> Fallible<R>,
+    ) -> Fallible<R> {
+        let data = source.read_all()?;
+        let mut included_from = vec![(self.source.clone(), None)];
+        included_from.extend(self.included_from.iter().cloned());
+        let mut config = xml::ParserConfig::default();
+        config.whitespace_to_characters = true;
+        config.cdata_to_characters = true;
+
+        let mut ctx = ParseContext {
+            reader: xml::EventReader::new_with_config(&data[..], config.clone()),
+            source,
+            tilesets: &mut *self.tilesets,
+            templates: &mut *self.templates,
+            warnings: &mut *self.warnings,
+            config: &config,
+            parseorder: 0,
+            path: Vec::new(),
+            included_from,
+        };
+        ctx.parse_root(root_tag, func)
+    }
+
+    pub fn warning(&mut self, msg: impl Into<String>) {
+        let diag = Diagnostic {
+            severity: Severity::Warning,
+            source: self.source.clone(),
+            position: None,
+            path: Vec::new(),
+            included_from: self.included_from.clone(),
+            message: msg.into(),
+        };
+        self.warnings.push(diag);
+    }
+
+    pub fn parseorder(&mut self) -> ParseOrder {
+        self.parseorder += 1;
+        ParseOrder(self.parseorder)
+    }
+}
+
+/// A JSON object along with the kind of Tiled element it describes, for error messages
+#[derive(Copy, Clone)]
+pub struct Obj<'a> {
+    map: &'a serde_json::Map<String, Value>,
+    kind: &'static str,
+}
+
+impl<'a> Obj<'a> {
+    fn new(value: &'a Value, kind: &'static str) -> Fallible<Obj<'a>> {
+        match value {
+            Value::Object(map) => Ok(Obj { map, kind }),
+            _ => Err(err_msg(format!("expected {} to be an object", kind))),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        match self.map.get(key) {
+            Some(Value::Null) | None => None,
+            Some(value) => Some(value),
+        }
+    }
+
+    fn missing(&self, key: &str) -> failure::Error {
+        err_msg(format!("missing attribute {}.{}", self.kind, key))
+    }
+
+    fn invalid(&self, key: &str) -> failure::Error {
+        err_msg(format!("invalid attribute {}.{}", self.kind, key))
+    }
+
+    fn out_of_range(&self, key: &str) -> failure::Error {
+        err_msg(format!("attribute {}.{} is out of range", self.kind, key))
+    }
+
+    fn opt_str(&self, key: &str) -> Fallible<Option<String>> {
+        match self.get(key) {
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(self.invalid(key)),
+            None => Ok(None),
+        }
+    }
+
+    fn str(&self, key: &str) -> Fallible<String> {
+        self.opt_str(key)?.ok_or_else(|| self.missing(key))
+    }
+
+    fn opt_f64(&self, key: &str) -> Fallible<Option<f64>> {
+        match self.get(key) {
+            Some(value) => value.as_f64().map(Some).ok_or_else(|| self.invalid(key)),
+            None => Ok(None),
+        }
+    }
+
+    fn opt_f32(&self, key: &str) -> Fallible<Option<f32>> {
+        Ok(self.opt_f64(key)?.map(|f| f as f32))
+    }
+
+    fn f32(&self, key: &str) -> Fallible<f32> {
+        self.opt_f32(key)?.ok_or_else(|| self.missing(key))
+    }
+
+    fn opt_i32(&self, key: &str) -> Fallible<Option<i32>> {
+        match self.get(key) {
+            Some(value) => {
+                let i = value.as_i64().ok_or_else(|| self.invalid(key))?;
+                narrow_i32(i)
+                    .map(Some)
+                    .ok_or_else(|| self.out_of_range(key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn i32(&self, key: &str) -> Fallible<i32> {
+        self.opt_i32(key)?.ok_or_else(|| self.missing(key))
+    }
+
+    fn opt_u32(&self, key: &str) -> Fallible<Option<u32>> {
+        match self.get(key) {
+            Some(value) => {
+                let i = value.as_u64().ok_or_else(|| self.invalid(key))?;
+                narrow_u32(i)
+                    .map(Some)
+                    .ok_or_else(|| self.out_of_range(key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn opt_bool(&self, key: &str) -> Fallible<Option<bool>> {
+        match self.get(key) {
+            Some(value) => value.as_bool().map(Some).ok_or_else(|| self.invalid(key)),
+            None => Ok(None),
+        }
+    }
+
+    fn opt_obj(&self, key: &str, kind: &'static str) -> Fallible<Option<Obj<'a>>> {
+        self.get(key).map(|value| Obj::new(value, kind)).transpose()
+    }
+
+    /// The array at `key`, or an empty one if it's missing
+    fn array(&self, key: &str) -> Fallible<&'a [Value]> {
+        match self.get(key) {
+            Some(Value::Array(values)) => Ok(values),
+            Some(_) => Err(self.invalid(key)),
+            None => Ok(&[]),
+        }
+    }
+
+    fn objs(&self, key: &str, kind: &'static str) -> Fallible<Vec<Obj<'a>>> {
+        self.array(key)?
+            .iter()
+            .map(|value| Obj::new(value, kind))
+            .collect()
+    }
+
+    fn opt_color(&self, key: &str) -> Fallible<Option<Color>> {
+        match self.opt_str(key)? {
+            Some(s) => Ok(Some(Color::from_str_argb(&s)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+pub fn parse_map(context: &mut JsonContext, obj: Obj) -> Fallible<Map> {
+    let obj = Obj { kind: "map", ..obj };
+
+    // Old versions of Tiled wrote the version as a number
+    let version = match obj.get("version") {
+        Some(Value::Number(n)) => n.to_string(),
+        _ => obj.str("version")?,
+    };
+    let tiledversion = obj.opt_str("tiledversion")?.unwrap_or_default();
+
+    let orientation = obj.str("orientation")?;
+    let orientation = match orientation.as_str() {
+        "orthogonal" => Orientation::Orthogonal,
+        "isometric" => Orientation::Isometric,
+        "staggered" => Orientation::Staggered,
+        "hexagonal" => Orientation::Hexagonal,
+        _ => {
+            return Err(err_msg(format!("Unknown orientation '{}'", orientation)));
+        }
+    };
+    let renderorder = obj.opt_str("renderorder")?;
+    let renderorder = match renderorder
+        .as_ref()
+        .map(|s| s.as_str())
+        .unwrap_or("right-down")
+    {
+        "right-down" => RenderOrder::RightDown,
+        "right-up" => RenderOrder::RightUp,
+        "left-down" => RenderOrder::LeftDown,
+        "left-up" => RenderOrder::LeftUp,
+        other => {
+            return Err(err_msg(format!("Unknown renderorder '{}'", other)));
+        }
+    };
+    let staggeraxis = match obj.opt_str("staggeraxis")?.as_ref().map(|s| s.as_str()) {
+        None => None,
+        Some("x") => Some(Axis::X),
+        Some("y") => Some(Axis::Y),
+        Some(a) => return Err(err_msg(format!("Unknown staggeraxis '{}'", a))),
+    };
+    let staggerindex = match obj.opt_str("staggerindex")?.as_ref().map(|s| s.as_str()) {
+        None => None,
+        Some("odd") => Some(StaggerIndex::Odd),
+        Some("even") => Some(StaggerIndex::Even),
+        Some(i) => return Err(err_msg(format!("Unknown staggerindex '{}'", i))),
+    };
+
+    let mut tilesets = obj
+        .objs("tilesets", "tileset")?
+        .into_iter()
+        .map(|ts| parse_map_tileset(context, ts))
+        .collect::<Fallible<Vec<_>>>()?;
+    let mut layers = parse_layers(context, obj)?;
+
+    let mut warnings = Vec::new();
+    for layer in layers.iter_mut() {
+        resolve_template_gids(&mut warnings, layer, &mut tilesets);
+    }
+    for warning in warnings {
+        context.warning(warning);
+    }
+
+    Ok(Map {
+        version,
+        tiledversion,
+        orientation,
+        renderorder,
+        width: obj.i32("width")?,
+        height: obj.i32("height")?,
+        tilewidth: obj.i32("tilewidth")?,
+        tileheight: obj.i32("tileheight")?,
+        infinite: obj.opt_bool("infinite")?.unwrap_or(false),
+        hexsidelength: obj.opt_f32("hexsidelength")?,
+        staggeraxis,
+        staggerindex,
+        backgroundcolor: obj.opt_color("backgroundcolor")?,
+        properties: parse_properties(obj)?,
+        tilesets,
+        layers,
+    })
+}
+
+fn parse_map_tileset(context: &mut JsonContext, obj: Obj) -> Fallible<MapTileset> {
+    let firstgid = GlobalTileId(
+        obj.opt_u32("firstgid")?
+            .ok_or_else(|| obj.missing("firstgid"))?,
+    );
+    let data = match obj.opt_str("source")? {
+        Some(source) => parse_tileset_file(context, &source)?,
+        None => Arc::new(parse_tileset(context, obj)?),
+    };
+
+    Ok(MapTileset { firstgid, data })
+}
+
+pub fn parse_tileset_file(context: &mut JsonContext, source: &str) -> Fallible<Arc<Tileset>> {
+    let source = context.source.relative(source);
+    if let Some(set) = context.tilesets.get(&source) {
+        return Ok(set.clone());
+    }
+
+    let set = if source.to_string().ends_with(".tsx") {
+        context.xml_subcontext(source.clone(), "tileset", |context, attrs| {
+            Ok(Arc::new(Tileset::parse_tag(context, attrs)?))
+        })?
+    } else {
+        context.subcontext(source.clone(), |context, obj| {
+            Ok(Arc::new(parse_tileset(context, obj)?))
+        })?
+    };
+
+    context.tilesets.insert(source, set.clone());
+    Ok(set)
+}
+
+pub fn parse_tileset(context: &mut JsonContext, obj: Obj) -> Fallible<Tileset> {
+    let obj = Obj {
+        kind: "tileset",
+        ..obj
+    };
+
+    let offset = match obj.opt_obj("tileoffset", "tileoffset")? {
+        Some(off) => TileOffset {
+            x: off.f32("x")?,
+            y: off.f32("y")?,
+        },
+        None => TileOffset::default(),
+    };
+    let image = parse_image(context, obj, "image")?;
+    let tiles = obj
+        .objs("tiles", "tile")?
+        .into_iter()
+        .map(|tile| parse_tile(context, tile))
+        .collect::<Fallible<_>>()?;
+    let terraintypes = obj
+        .objs("terrains", "terrain")?
+        .into_iter()
+        .map(|terrain| {
+            Ok(Terrain {
+                name: terrain.str("name")?,
+                tile: terrain.opt_i32("tile")?.unwrap_or(-1),
+                properties: parse_properties(terrain)?,
+            })
+        })
+        .collect::<Fallible<_>>()?;
+    let wangsets = obj
+        .objs("wangsets", "wangset")?
+        .into_iter()
+        .map(parse_wangset)
+        .collect::<Fallible<_>>()?;
+
+    Ok(Tileset {
+        name: obj.str("name")?,
+        tilewidth: obj.i32("tilewidth")?,
+        tileheight: obj.i32("tileheight")?,
+        tilecount: obj.i32("tilecount")?,
+        columns: obj.i32("columns")?,
+        spacing: obj.opt_i32("spacing")?.unwrap_or(0),
+        margin: obj.opt_i32("margin")?.unwrap_or(0),
+        offset,
+        image,
+        tiles,
+        terraintypes,
+        wangsets,
+    })
+}
+
+fn parse_wangset(obj: Obj) -> Fallible<WangSet> {
+    let parse_colors = |key: &str| {
+        obj.objs(key, "wangcolor")?
+            .into_iter()
+            .map(|color| {
+                Ok(WangColor {
+                    name: color.str("name")?,
+                    color: color
+                        .opt_color("color")?
+                        .ok_or_else(|| color.missing("color"))?,
+                    tile: color.opt_i32("tile")?.unwrap_or(-1),
+                    probability: color.opt_f32("probability")?.unwrap_or(1.0),
+                    properties: parse_properties(color)?,
+                })
+            })
+            .collect::<Fallible<Vec<_>>>()
+    };
+    let colors = parse_colors("colors")?;
+    let edge_colors = parse_colors("edgecolors")?;
+    let corner_colors = parse_colors("cornercolors")?;
+
+    let mut tiles = obj
+        .objs("wangtiles", "wangtile")?
+        .into_iter()
+        .map(|tile| {
+            let tileid = LocalTileId(
+                tile.opt_u32("tileid")?
+                    .ok_or_else(|| tile.missing("tileid"))?,
+            );
+            let ids = tile
+                .array("wangid")?
+                .iter()
+                .map(|id| {
+                    let id = id.as_u64().ok_or_else(|| tile.invalid("wangid"))?;
+                    narrow_u8(id).ok_or_else(|| tile.out_of_range("wangid"))
+                })
+                .collect::<Fallible<Vec<_>>>()?;
+            if ids.len() != 8 {
+                return Err(tile.invalid("wangid"));
+            }
+            let mut wangid = [0; 8];
+            wangid.copy_from_slice(&ids);
+            Ok(WangTile { tileid, wangid })
+        })
+        .collect::<Fallible<Vec<_>>>()?;
+
+    let wangtype = match obj.opt_str("type")? {
+        Some(wangtype) => WangType::parse(&wangtype)?,
+        None => WangType::legacy(!edge_colors.is_empty(), !corner_colors.is_empty()),
+    };
+    let colors = if edge_colors.is_empty() && corner_colors.is_empty() {
+        colors
+    } else {
+        merge_legacy_colors(edge_colors, corner_colors, &mut tiles)
+    };
+
+    Ok(WangSet {
+        name: obj.str("name")?,
+        wangtype,
+        tile: obj.opt_i32("tile")?.unwrap_or(-1),
+        properties: parse_properties(obj)?,
+        colors,
+        tiles,
+    })
+}
+
+fn parse_tile(context: &mut JsonContext, obj: Obj) -> Fallible<Tile> {
+    let id = LocalTileId(obj.opt_u32("id")?.ok_or_else(|| obj.missing("id"))?);
+    let tiletype = match obj.opt_str("type")? {
+        Some(kind) => Some(kind),
+        None => obj.opt_str("class")?,
+    };
+    let terrain = match obj.get("terrain") {
+        Some(Value::Array(corners)) => Some(
+            corners
+                .iter()
+                .map(|c| c.as_i64().map(|i| i.to_string()).unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        Some(_) => return Err(obj.invalid("terrain")),
+        None => None,
+    };
+    let image = parse_image(context, obj, "image")?.map(Arc::new);
+    let objects = match obj.opt_obj("objectgroup", "objectgroup")? {
+        Some(group) => Some(Arc::new(parse_object_group(context, group)?)),
+        None => None,
+    };
+    let frames = obj
+        .objs("animation", "frame")?
+        .into_iter()
+        .map(|frame| {
+            let tileid = LocalTileId(
+                frame
+                    .opt_u32("tileid")?
+                    .ok_or_else(|| frame.missing("tileid"))?,
+            );
+            let duration = frame.f32("duration")? as f64;
+            let duration = std::time::Duration::from_micros((duration * 1000.0) as u64);
+            Ok(Frame { tileid, duration })
+        })
+        .collect::<Fallible<Vec<_>>>()?;
+    let animation = if frames.is_empty() {
+        None
+    } else {
+        Some(Arc::new(Animation { frames }))
+    };
+
+    Ok(Tile {
+        id,
+        tiletype,
+        terrain,
+        probability: obj.opt_f32("probability")?,
+        properties: parse_properties(obj)?,
+        image,
+        objects,
+        animation,
+    })
+}
+
+/// JSON flattens the image tag into its parent as `image`, `imagewidth`, `imageheight` and
+/// `transparentcolor`
+fn parse_image(context: &mut JsonContext, obj: Obj, key: &str) -> Fallible<Option<Image>> {
+    let source = match obj.opt_str(key)? {
+        Some(source) => source,
+        None => return Ok(None),
+    };
+
+    let transparent = obj
+        .opt_str("transparentcolor")?
+        .map(|s| s.parse::<TransColor>())
+        .transpose()?;
+
+    Ok(Some(Image {
+        source: context.source.relative(&source),
+        width: obj.opt_i32("imagewidth")?.unwrap_or(0),
+        height: obj.opt_i32("imageheight")?.unwrap_or(0),
+        transparent,
+    }))
+}
+
+fn parse_layers(context: &mut JsonContext, obj: Obj) -> Fallible<Vec<Layer>> {
+    obj.objs("layers", "layer")?
+        .into_iter()
+        .map(|layer| parse_layer(context, layer))
+        .collect()
+}
+
+fn parse_layer(context: &mut JsonContext, obj: Obj) -> Fallible<Layer> {
+    let parse_order = context.parseorder();
+    let id = obj.opt_i32("id")?.unwrap_or(0);
+    let name = obj.opt_str("name")?.unwrap_or_default();
+    let offsetx = obj.opt_f32("offsetx")?.unwrap_or(0.0);
+    let offsety = obj.opt_f32("offsety")?.unwrap_or(0.0);
+    let opacity = obj.opt_f32("opacity")?.unwrap_or(1.0);
+    let visible = obj.opt_bool("visible")?.unwrap_or(true);
+    let properties = parse_properties(obj)?;
+
+    let kind = obj.str("type")?;
+    let layer = match kind.as_str() {
+        "tilelayer" => {
+            let obj = Obj {
+                kind: "tilelayer",
+                ..obj
+            };
+            Layer::Tile(TileLayer {
+                parse_order,
+                id,
+                name,
+                width: obj.i32("width")?,
+                height: obj.i32("height")?,
+                offsetx,
+                offsety,
+                opacity,
+                visible,
+                properties,
+                data: parse_data(obj)?,
+            })
+        }
+        "objectgroup" => Layer::Object(parse_object_group(context, obj)?),
+        "imagelayer" => {
+            let obj = Obj {
+                kind: "imagelayer",
+                ..obj
+            };
+            let image = parse_image(context, obj, "image")?
+                .ok_or_else(|| err_msg("imagelayer is missing its image"))?;
+            Layer::Image(ImageLayer {
+                parse_order,
+                id,
+                name,
+                offsetx,
+                offsety,
+                opacity,
+                visible,
+                parallaxx: obj.opt_f32("parallaxx")?.unwrap_or(1.0),
+                parallaxy: obj.opt_f32("parallaxy")?.unwrap_or(1.0),
+                repeatx: obj.opt_bool("repeatx")?.unwrap_or(false),
+                repeaty: obj.opt_bool("repeaty")?.unwrap_or(false),
+                properties,
+                image,
+            })
+        }
+        "group" => Layer::Group(GroupLayer {
+            parse_order,
+            id,
+            name,
+            offsetx,
+            offsety,
+            opacity,
+            visible,
+            properties,
+            layers: parse_layers(context, obj)?,
+        }),
+        _ => return Err(err_msg(format!("Unknown layer type '{}'", kind))),
+    };
+
+    Ok(layer)
+}
+
+fn parse_data(obj: Obj) -> Fallible<Data> {
+    let is_base64 = obj
+        .opt_str("encoding")?
+        .map(|enc| enc == "base64")
+        .unwrap_or(false);
+    // Tiled writes an empty string for uncompressed data
+    let compression = obj.opt_str("compression")?.filter(|c| !c.is_empty());
+    let compression = compression.as_ref().map(|s| s.as_str());
+
+    let tiles = |obj: Obj| -> Fallible<Vec<GlobalTileId>> {
+        match obj.get("data") {
+            Some(Value::String(content)) if is_base64 => {
+                decode_tiles(content, is_base64, compression)
+            }
+            Some(Value::Array(gids)) => gids
+                .iter()
+                .map(|gid| {
+                    let gid = gid.as_u64().ok_or_else(|| obj.invalid("data"))?;
+                    let gid = narrow_u32(gid).ok_or_else(|| obj.out_of_range("data"))?;
+                    Ok(GlobalTileId(gid))
+                })
+                .collect(),
+            Some(_) => Err(obj.invalid("data")),
+            None => Err(obj.missing("data")),
+        }
+    };
+
+    // Infinite maps always have a chunks array, even if it's empty
+    if obj.get("chunks").is_some() {
+        let chunks = obj
+            .objs("chunks", "chunk")?
+            .into_iter()
+            .map(|chunk| {
+                Ok(Chunk {
+                    x: chunk.i32("x")?,
+                    y: chunk.i32("y")?,
+                    width: chunk.i32("width")?,
+                    height: chunk.i32("height")?,
+                    data: tiles(chunk)?,
+                })
+            })
+            .collect::<Fallible<_>>()?;
+        Ok(Data::Chunked(chunks))
+    } else {
+        Ok(Data::Plain(tiles(obj)?))
+    }
+}
+
+fn parse_object_group(context: &mut JsonContext, obj: Obj) -> Fallible<ObjectGroup> {
+    let obj = Obj {
+        kind: "objectgroup",
+        ..obj
+    };
+    let parse_order = context.parseorder();
+
+    let draworder = match obj
+        .opt_str("draworder")?
+        .as_ref()
+        .map(|s| s.as_str())
+        .unwrap_or("")
+    {
+        "index" => DrawOrder::Index,
+        _ => DrawOrder::TopDown,
+    };
+    let objects = obj
+        .objs("objects", "object")?
+        .into_iter()
+        .map(|object| parse_object(context, object))
+        .collect::<Fallible<_>>()?;
+
+    Ok(ObjectGroup {
+        parse_order,
+        id: obj.opt_i32("id")?,
+        name: obj.opt_str("name")?,
+        color: obj.opt_color("color")?,
+        x: obj.opt_f32("x")?.unwrap_or(0.0),
+        y: obj.opt_f32("y")?.unwrap_or(0.0),
+        opacity: obj.opt_f32("opacity")?.unwrap_or(1.0),
+        visible: obj.opt_bool("visible")?.unwrap_or(true),
+        offsetx: obj.opt_f32("offsetx")?.unwrap_or(0.0),
+        offsety: obj.opt_f32("offsety")?.unwrap_or(0.0),
+        draworder,
+        properties: parse_properties(obj)?,
+        objects,
+    })
+}
+
+fn parse_object(context: &mut JsonContext, obj: Obj) -> Fallible<Object> {
+    use std::f32::consts::PI;
+
+    let obj = Obj {
+        kind: "object",
+        ..obj
+    };
+    let parse_order = context.parseorder();
+
+    // Anything the instance doesn't specify comes from the template, same as the XML parser
+    let template = match obj.opt_str("template")? {
+        Some(template) => Some(parse_template_file(context, &template)?),
+        None => None,
+    };
+    let base = template.as_ref().map(|t| &t.object);
+
+    let kind = match obj.opt_str("type")?.filter(|k| !k.is_empty()) {
+        Some(kind) => Some(kind),
+        None => obj.opt_str("class")?.filter(|k| !k.is_empty()),
+    };
+    let name = obj.opt_str("name")?.filter(|n| !n.is_empty());
+    let rotation = match obj.opt_f32("rotation")? {
+        Some(rotation) => rotation * PI / 180.0,
+        None => base.map(|b| b.rotation).unwrap_or(0.0),
+    };
+
+    let overrides = parse_properties(obj)?;
+    let mut properties = base.map(|b| b.properties.clone()).unwrap_or_default();
+    properties.properties.extend(overrides.properties);
+
+    let text = match obj.opt_obj("text", "text")? {
+        Some(text) => Some(parse_text(text)?),
+        None => base.and_then(|b| b.text.clone()),
+    };
+
+    let points = |key| -> Fallible<Vec<math2d::Vector2f>> {
+        obj.objs(key, "point")?
+            .into_iter()
+            .map(|p| Ok([p.f32("x")?, p.f32("y")?].into()))
+            .collect()
+    };
+    let shape = if obj.opt_bool("ellipse")?.unwrap_or(false) {
+        Shape::Ellipse
+    } else if obj.opt_bool("point")?.unwrap_or(false) {
+        Shape::Point
+    } else if obj.get("polygon").is_some() {
+        Shape::Polygon(points("polygon")?)
+    } else if obj.get("polyline").is_some() {
+        Shape::Polyline(points("polyline")?)
+    } else if let Some(base) = base {
+        base.shape.clone()
+    } else {
+        Shape::Rectangle
+    };
+
+    Ok(Object {
+        parse_order,
+        id: obj.opt_i32("id")?.unwrap_or(0),
+        name: name.or_else(|| base.and_then(|b| b.name.clone())),
+        kind: kind.or_else(|| base.and_then(|b| b.kind.clone())),
+        x: obj.opt_f32("x")?.unwrap_or(0.0),
+        y: obj.opt_f32("y")?.unwrap_or(0.0),
+        width: obj
+            .opt_f32("width")?
+            .or(base.map(|b| b.width))
+            .unwrap_or(0.0),
+        height: obj
+            .opt_f32("height")?
+            .or(base.map(|b| b.height))
+            .unwrap_or(0.0),
+        rotation,
+        gid: obj.opt_u32("gid")?.map(GlobalTileId),
+        visible: obj
+            .opt_bool("visible")?
+            .or(base.map(|b| b.visible))
+            .unwrap_or(true),
+        template,
+        properties,
+        shape,
+        text,
+    })
+}
+
+fn parse_template_file(context: &mut JsonContext, source: &str) -> Fallible<Arc<Template>> {
+    let source = context.source.relative(source);
+    if let Some(template) = context.templates.get(&source) {
+        return Ok(template.clone());
+    }
+
+    let template = if source.to_string().ends_with(".tx") {
+        context.xml_subcontext(source.clone(), "template", |context, attrs| {
+            Ok(Arc::new(Template::parse_tag(context, attrs)?))
+        })?
+    } else {
+        context.subcontext(source.clone(), |context, obj| {
+            let tileset = match obj.opt_obj("tileset", "tileset")? {
+                Some(ts) => Some(parse_map_tileset(context, ts)?),
+                None => None,
+            };
+            let object = obj
+                .opt_obj("object", "object")?
+                .ok_or_else(|| err_msg("template is missing its object"))?;
+            let object = parse_object(context, object)?;
+            Ok(Arc::new(Template {
+                source: context.source.clone(),
+                tileset,
+                object,
+            }))
+        })?
+    };
+
+    context.templates.insert(source, template.clone());
+    Ok(template)
+}
+
+fn parse_text(obj: Obj) -> Fallible<Text> {
+    let halign = match obj
+        .opt_str("halign")?
+        .as_ref()
+        .map(|s| s.as_str())
+        .unwrap_or("")
+    {
+        "center" => HAlign::Center,
+        "right" => HAlign::Right,
+        "justify" => HAlign::Justified,
+        _ => HAlign::Left,
+    };
+    let valign = match obj
+        .opt_str("valign")?
+        .as_ref()
+        .map(|s| s.as_str())
+        .unwrap_or("")
+    {
+        "center" => VAlign::Center,
+        "bottom" => VAlign::Bottom,
+        _ => VAlign::Top,
+    };
+
+    Ok(Text {
+        content: obj.opt_str("text")?.unwrap_or_default(),
+        fontfamily: obj.opt_str("fontfamily")?,
+        pixelsize: obj.opt_f32("pixelsize")?.unwrap_or(16.0),
+        wrap: obj.opt_bool("wrap")?.unwrap_or(false),
+        color: obj.opt_color("color")?.unwrap_or(Color::BLACK),
+        bold: obj.opt_bool("bold")?.unwrap_or(false),
+        italic: obj.opt_bool("italic")?.unwrap_or(false),
+        underline: obj.opt_bool("underline")?.unwrap_or(false),
+        strikeout: obj.opt_bool("strikeout")?.unwrap_or(false),
+        kerning: obj.opt_bool("kerning")?.unwrap_or(true),
+        halign,
+        valign,
+    })
+}
+
+fn parse_properties(obj: Obj) -> Fallible<Properties> {
+    let properties = obj
+        .objs("properties", "property")?
+        .into_iter()
+        .map(parse_property)
+        .collect::<Fallible<_>>()?;
+
+    Ok(Properties { properties })
+}
+
+fn parse_property(obj: Obj) -> Fallible<(String, Property)> {
+    let name = obj.str("name")?;
+    let kind = obj.opt_str("type")?.unwrap_or_else(|| "string".into());
+    let value = obj.get("value").ok_or_else(|| obj.missing("value"))?;
+    let invalid = || obj.invalid("value");
+
+    if kind == "class" {
+        let propertytype = obj.opt_str("propertytype")?.unwrap_or_default();
+        let properties = parse_class_members(Obj::new(value, "class")?)?;
+        let prop = Property::Class {
+            propertytype,
+            properties,
+        };
+        return Ok((name, prop));
+    }
+
+    let prop = match kind.as_str() {
+        "string" => Property::String(value.as_str().ok_or_else(invalid)?.into()),
+        "int" => Property::Int(value.as_i64().ok_or_else(invalid)?),
+        "float" => Property::Float(value.as_f64().ok_or_else(invalid)? as f32),
+        "bool" => Property::Bool(value.as_bool().ok_or_else(invalid)?),
+        "color" => Property::Color(Color::from_str_argb(value.as_str().ok_or_else(invalid)?)?),
+        "file" => Property::File(value.as_str().ok_or_else(invalid)?.into()),
+        "object" => {
+            let id = value.as_i64().ok_or_else(invalid)?;
+            Property::Object(narrow_i32(id).ok_or_else(|| obj.out_of_range("value"))?)
+        }
+
+        _ => return Err(err_msg(format!("Unknown Tiled property type `{}`", kind))),
+    };
+
+    Ok((name, prop))
+}
+
+/// JSON class values are plain objects without member types, so the types are inferred from
+/// the values. Nested objects become nested classes.
+fn parse_class_members(obj: Obj) -> Fallible<Properties> {
+    let properties = obj
+        .map
+        .iter()
+        .map(|(name, value)| {
+            let prop = match value {
+                Value::String(s) => Property::String(s.clone()),
+                Value::Bool(b) => Property::Bool(*b),
+                Value::Number(n) => match n.as_i64() {
+                    Some(i) => Property::Int(i),
+                    None => Property::Float(n.as_f64().unwrap_or(0.0) as f32),
+                },
+                Value::Object(_) => Property::Class {
+                    propertytype: String::new(),
+                    properties: parse_class_members(Obj::new(value, "class")?)?,
+                },
+                _ => return Err(obj.invalid(name)),
+            };
+            Ok((name.clone(), prop))
+        })
+        .collect::<Fallible<_>>()?;
+
+    Ok(Properties { properties })
+}
+
+// `as` would silently wrap integers that don't fit, turning a bad id or size into a wrong but
+// valid looking one, so these check the bounds instead
+
+fn narrow_i32(i: i64) -> Option<i32> {
+    if i >= i64::from(i32::min_value()) && i <= i64::from(i32::max_value()) {
+        Some(i as i32)
+    } else {
+        None
+    }
+}
+
+fn narrow_u32(i: u64) -> Option<u32> {
+    if i <= u64::from(u32::max_value()) {
+        Some(i as u32)
+    } else {
+        None
+    }
+}
+
+fn narrow_u8(i: u64) -> Option<u8> {
+    if i <= u64::from(u8::max_value()) {
+        Some(i as u8)
+    } else {
+        None
+    }
+}
@@ -37,7 +37,7 @@ impl GroupLayer {
                 <objectgroup> => Layer::parse_obj,
                 <imagelayer> => Layer::parse_img,
                 <group> => Layer::parse_grp,
-            </imagelayer>
+            </group>
         }
 
         let offsetx = offsetx.unwrap_or(0.0);
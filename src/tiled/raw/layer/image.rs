@@ -15,6 +15,10 @@ pub struct ImageLayer {
     pub offsety: f32,
     pub opacity: f32,
     pub visible: bool,
+    pub parallaxx: f32,
+    pub parallaxy: f32,
+    pub repeatx: bool,
+    pub repeaty: bool,
     pub properties: Properties,
     pub image: Image,
 }
@@ -30,7 +34,9 @@ impl ImageLayer {
             <imagelayer
                     id="id"(i32) name="name"(String)
                     ?offsetx="offsetx"(f32) ?offsety="offsety"(f32)
-                    ?opacity="opacity"(f32) ?visible="visible"(i32)>
+                    ?opacity="opacity"(f32) ?visible="visible"(i32)
+                    ?parallaxx="parallaxx"(f32) ?parallaxy="parallaxy"(f32)
+                    ?repeatx="repeatx"(i32) ?repeaty="repeaty"(i32)>
                 <properties> => Properties::parse_tag,
                 <image> => Image::parse_tag,
             </imagelayer>
@@ -40,6 +46,10 @@ impl ImageLayer {
         let offsety = offsety.unwrap_or(0.0);
         let opacity = opacity.unwrap_or(1.0);
         let visible = visible.map(|i| i != 0).unwrap_or(true);
+        let parallaxx = parallaxx.unwrap_or(1.0);
+        let parallaxy = parallaxy.unwrap_or(1.0);
+        let repeatx = repeatx.map(|i| i != 0).unwrap_or(false);
+        let repeaty = repeaty.map(|i| i != 0).unwrap_or(false);
         let properties = properties.pop().unwrap_or_default();
         let image = image
             .pop()
@@ -53,6 +63,10 @@ impl ImageLayer {
             offsety,
             opacity,
             visible,
+            parallaxx,
+            parallaxy,
+            repeatx,
+            repeaty,
             properties,
             image,
         })
@@ -11,6 +11,8 @@ pub struct TileLayer {
     pub parse_order: ParseOrder,
     pub id: i32,
     pub name: String,
+    pub width: i32,
+    pub height: i32,
     pub offsetx: f32,
     pub offsety: f32,
     pub opacity: f32,
@@ -29,6 +31,7 @@ impl TileLayer {
             context; attrs;
             <layer
                 id="id"(i32) name="name"(String)
+                width="width"(i32) height="height"(i32)
                 ?offsetx="offsetx"(f32) ?offsety="offsety"(f32)
                 ?opacity="opacity"(f32) ?visible="visible"(i32)
                 >
@@ -48,6 +51,8 @@ impl TileLayer {
             parse_order,
             id,
             name,
+            width,
+            height,
             offsetx,
             offsety,
             opacity,
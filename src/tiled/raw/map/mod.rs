@@ -2,7 +2,9 @@ use crate::tiled::raw::properties::Properties;
 use crate::tiled::raw::context::ParseContext;
 use crate::tiled::raw::layer::Layer;
 use crate::tiled::raw::tileset::MapTileset;
-use crate::tiled::raw::RenderOrder;
+use crate::tiled::raw::{GlobalTileId, RenderOrder};
+
+use std::sync::Arc;
 
 use failure::Fallible;
 use xml::attribute as xa;
@@ -20,7 +22,7 @@ pub struct Map {
     pub infinite: bool,
     pub hexsidelength: Option<f32>,
     pub staggeraxis: Option<Axis>,
-    pub staggerindex: Option<i32>,
+    pub staggerindex: Option<StaggerIndex>,
     pub backgroundcolor: Option<math2d::Color>,
 
     pub properties: Properties,
@@ -42,7 +44,7 @@ impl Map {
                 ?infinite="infinite"(i32)
                 ?hexsidelength="hexsidelength"(f32)
                 ?staggeraxis="staggeraxis"(String)
-                ?staggerindex="staggerindex"(i32)
+                ?staggerindex="staggerindex"(String)
                 ?backgroundcolor="backgroundcolor"(String)>
 
                 <properties> => Properties::parse_tag,
@@ -85,12 +87,26 @@ impl Map {
             Some("y") => Some(Axis::Y),
             Some(a) => return Err(failure::err_msg(format!("Unknown staggeraxis '{}'", a))),
         };
+        let staggerindex = match staggerindex.as_ref().map(|s| s.as_str()) {
+            None => None,
+            Some("odd") => Some(StaggerIndex::Odd),
+            Some("even") => Some(StaggerIndex::Even),
+            Some(i) => return Err(failure::err_msg(format!("Unknown staggerindex '{}'", i))),
+        };
         let backgroundcolor = backgroundcolor
             .map(|s| Color::from_str_argb(&s))
             .transpose()?;
         let properties = properties.pop().unwrap_or_default();
-        let tilesets = tileset;
-        let layers = Layer::combine(&mut [layer, objectgroup, imagelayer, group]);
+        let mut tilesets = tileset;
+        let mut layers = Layer::combine(&mut [layer, objectgroup, imagelayer, group]);
+
+        let mut warnings = Vec::new();
+        for layer in layers.iter_mut() {
+            resolve_template_gids(&mut warnings, layer, &mut tilesets);
+        }
+        for warning in warnings {
+            context.warning(warning);
+        }
 
         Ok(Map {
             version,
@@ -113,6 +129,76 @@ impl Map {
     }
 }
 
+/// Translates gids that tile objects inherit from their template into the map's gid space.
+/// Template tilesets the map doesn't reference yet get appended to the map's tilesets.
+pub(in crate::tiled::raw) fn resolve_template_gids(
+    warnings: &mut Vec<String>,
+    layer: &mut Layer,
+    tilesets: &mut Vec<MapTileset>,
+) {
+    let objects = match layer {
+        Layer::Object(group) => &mut group.objects,
+        Layer::Group(group) => {
+            for layer in group.layers.iter_mut() {
+                resolve_template_gids(warnings, layer, tilesets);
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    for object in objects.iter_mut().filter(|obj| obj.gid.is_none()) {
+        let template = match &object.template {
+            Some(template) => template,
+            None => continue,
+        };
+        let (tileset, gid) = match (&template.tileset, template.object.gid) {
+            (Some(tileset), Some(gid)) => (tileset, gid),
+            (None, Some(_)) => {
+                warnings.push(format!(
+                    "template {} has a gid but no tileset",
+                    template.source
+                ));
+                continue;
+            }
+            _ => continue,
+        };
+
+        let local = match gid.id().0.checked_sub(tileset.firstgid.0) {
+            Some(local) => local,
+            None => {
+                warnings.push(format!(
+                    "template {} has a gid outside of its tileset",
+                    template.source
+                ));
+                continue;
+            }
+        };
+        let existing = tilesets
+            .iter()
+            .find(|ts| Arc::ptr_eq(&ts.data, &tileset.data))
+            .map(|ts| ts.firstgid);
+        let firstgid = match existing {
+            Some(firstgid) => firstgid,
+            None => {
+                let firstgid = tilesets
+                    .iter()
+                    .map(|ts| ts.firstgid.0 + ts.data.tilecount as u32)
+                    .max()
+                    .unwrap_or(1);
+                let firstgid = GlobalTileId(firstgid);
+                tilesets.push(MapTileset {
+                    firstgid,
+                    data: tileset.data.clone(),
+                });
+                firstgid
+            }
+        };
+
+        object.gid = Some(GlobalTileId((firstgid.0 + local) | gid.flags()));
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Orientation {
     Orthogonal,
@@ -126,3 +212,9 @@ pub enum Axis {
     X,
     Y,
 }
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum StaggerIndex {
+    Odd,
+    Even,
+}
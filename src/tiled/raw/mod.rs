@@ -1,11 +1,13 @@
 pub use self::{
     context::{ParseContext, ParseOrder, ParseResult, },
     data::{Chunk, Data},
-    image::Image,
+    diagnostic::{Diagnostic, Severity},
+    image::{Image, TransColor},
     layer::{group::GroupLayer, image::ImageLayer, tile::TileLayer, Layer},
     map::Map,
     objects::{shape::Shape, text::Text, Object, ObjectGroup},
     properties::{Properties, Property},
+    template::Template,
     tileset::{
         animation::{Animation, Frame},
         offset::TileOffset,
@@ -19,11 +21,14 @@ pub mod helpers;
 
 pub mod context;
 pub mod data;
+pub mod diagnostic;
 pub mod image;
+pub mod json;
 pub mod layer;
 pub mod map;
 pub mod objects;
 pub mod properties;
+pub mod template;
 pub mod tileset;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -32,6 +37,26 @@ pub struct LocalTileId(pub u32);
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct GlobalTileId(pub u32);
 
+impl GlobalTileId {
+    pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+    pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+    pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+    pub const ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+
+    /// All of the bits Tiled packs into the high end of a gid
+    pub const FLAG_MASK: u32 = 0xF000_0000;
+
+    /// The gid with all of the flip/rotation bits cleared
+    pub fn id(self) -> GlobalTileId {
+        GlobalTileId(self.0 & !Self::FLAG_MASK)
+    }
+
+    /// Just the flip/rotation bits of the gid
+    pub fn flags(self) -> u32 {
+        self.0 & Self::FLAG_MASK
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DrawOrder {
     Index,
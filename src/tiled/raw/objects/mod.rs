@@ -1,12 +1,14 @@
-use crate::tiled::source::Source;
 use crate::tiled::raw::context::ParseContext;
 use crate::tiled::raw::context::ParseOrder;
 use crate::tiled::raw::objects::shape::Shape;
 use crate::tiled::raw::objects::text::Text;
 use crate::tiled::raw::properties::Properties;
+use crate::tiled::raw::template::Template;
 use crate::tiled::raw::DrawOrder;
 use crate::tiled::raw::GlobalTileId;
 
+use std::sync::Arc;
+
 use failure::Fallible;
 use xml::attribute as xa;
 
@@ -103,9 +105,11 @@ pub struct Object {
     pub width: f32,
     pub height: f32,
     pub rotation: f32,
+    /// Only the instance's own gid. A gid inherited from the template is relative to the
+    /// template's tileset and gets resolved by `Map::parse_tag`.
     pub gid: Option<GlobalTileId>,
     pub visible: bool,
-    pub template: Option<Source>,
+    pub template: Option<Arc<Template>>,
     pub properties: Properties,
     pub shape: Shape,
     pub text: Option<Text>,
@@ -118,7 +122,7 @@ impl Object {
         let parse_order = context.parseorder();
         parse_tag! {
             context; attrs;
-            <object id="id"(i32) x="x"(f32) y="y"(f32)
+            <object ?id="id"(i32) ?x="x"(f32) ?y="y"(f32)
                     ?name="name"(String) ?kind="type"(String)
                     ?width="width"(f32) ?height="height"(f32)
                     ?rotation="rotation"(f32)
@@ -135,13 +139,35 @@ impl Object {
             </object>
         }
 
-        let width = width.unwrap_or(0.0);
-        let height = height.unwrap_or(0.0);
-        let rotation = rotation.unwrap_or(0.0) * PI / 180.0;
-        let visible = visible.map(|i| i != 0).unwrap_or(true);
-        let template = template.map(|s| context.source.relative(&s));
-        let properties = properties.pop().unwrap_or_default();
-        let text = text.pop();
+        // Anything the instance doesn't specify comes from the template. Templates themselves
+        // don't have an id or position.
+        let template = match template {
+            Some(template) => Some(Template::parse_file(context, &template)?),
+            None => None,
+        };
+        let base = template.as_ref().map(|t| &t.object);
+
+        let id = id.unwrap_or(0);
+        let x = x.unwrap_or(0.0);
+        let y = y.unwrap_or(0.0);
+        let name = name.or_else(|| base.and_then(|b| b.name.clone()));
+        let kind = kind.or_else(|| base.and_then(|b| b.kind.clone()));
+        let width = width.or(base.map(|b| b.width)).unwrap_or(0.0);
+        let height = height.or(base.map(|b| b.height)).unwrap_or(0.0);
+        let rotation = match rotation {
+            Some(rotation) => rotation * PI / 180.0,
+            None => base.map(|b| b.rotation).unwrap_or(0.0),
+        };
+        let visible = match visible {
+            Some(visible) => visible != 0,
+            None => base.map(|b| b.visible).unwrap_or(true),
+        };
+
+        let overrides = properties.pop().unwrap_or_default();
+        let mut properties = base.map(|b| b.properties.clone()).unwrap_or_default();
+        properties.properties.extend(overrides.properties);
+
+        let text = text.pop().or_else(|| base.and_then(|b| b.text.clone()));
 
         let shape = if let Some(ellipse) = ellipse.pop() {
             ellipse
@@ -151,6 +177,8 @@ impl Object {
             polygon
         } else if let Some(polyline) = polyline.pop() {
             polyline
+        } else if let Some(base) = base {
+            base.shape.clone()
         } else {
             Shape::Rectangle
         };
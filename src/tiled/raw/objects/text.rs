@@ -3,7 +3,7 @@ use crate::tiled::raw::context::ParseContext;
 use failure::Fallible;
 use xml::attribute as xa;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Text {
     pub content: String,
     pub fontfamily: Option<String>,
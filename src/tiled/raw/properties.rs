@@ -47,6 +47,13 @@ pub enum Property {
     Bool(bool),
     Color(math2d::Color),
     File(String),
+    /// Id of the referenced object, 0 if unset
+    Object(i32),
+    /// A custom class with its own set of members
+    Class {
+        propertytype: String,
+        properties: Properties,
+    },
 }
 
 impl std::fmt::Debug for Property {
@@ -58,6 +65,11 @@ impl std::fmt::Debug for Property {
             Property::Bool(value) => std::fmt::Debug::fmt(value, fmt),
             Property::Color(value) => std::fmt::Debug::fmt(value, fmt),
             Property::File(value) => std::fmt::Debug::fmt(value, fmt),
+            Property::Object(value) => write!(fmt, "Object({})", value),
+            Property::Class {
+                propertytype,
+                properties,
+            } => write!(fmt, "{}{:?}", propertytype, properties),
         }
     }
 }
@@ -69,10 +81,16 @@ impl Property {
     ) -> Fallible<(String, Property)> {
         parse_tag! {
             context; attrs;
-            <property name="name"(String) value="value"(String) ?kind="type"(String) />
+            <property name="name"(String) ?value="value"(String) ?kind="type"(String)
+                      ?propertytype="propertytype"(String)>
+                content,
+                <properties> => Properties::parse_tag,
+            </property>
         };
 
         let kind = kind.unwrap_or_else(|| "string".into());
+        // Multi-line strings are stored as the tag's content instead of the value attribute
+        let value = value.unwrap_or(content);
 
         Ok(match kind.as_str() {
             "string" => (name, Property::String(value)),
@@ -81,6 +99,16 @@ impl Property {
             "bool" => (name, Property::Bool(value.parse()?)),
             "color" => (name, Property::Color(math2d::Color::from_str_argb(&value)?)),
             "file" => (name, Property::File(value)),
+            "object" => (name, Property::Object(value.parse()?)),
+            "class" => {
+                let propertytype = propertytype.unwrap_or_default();
+                let properties = properties.pop().unwrap_or_default();
+                let prop = Property::Class {
+                    propertytype,
+                    properties,
+                };
+                (name, prop)
+            }
 
             _ => return Err(err_msg(format!("Unknown Tiled property type `{}`", kind))),
         })
@@ -0,0 +1,60 @@
+use crate::tiled::raw::context::ParseContext;
+use crate::tiled::raw::objects::Object;
+use crate::tiled::raw::tileset::MapTileset;
+use crate::tiled::source::Source;
+
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible};
+use xml::attribute as xa;
+
+#[derive(Debug)]
+pub struct Template {
+    pub source: Source,
+    /// The tileset the template's gid refers to, if it is a tile object
+    pub tileset: Option<MapTileset>,
+    pub object: Object,
+}
+
+impl Template {
+    pub fn parse_file(context: &mut ParseContext, source: &str) -> Fallible<Arc<Template>> {
+        let source = context.source.relative(source);
+        if let Some(template) = context.templates.get(&source) {
+            return Ok(template.clone());
+        }
+
+        let data = source.read_all()?;
+        context.subcontext(&data, source, "template", |context, attrs| {
+            let template = Arc::new(Template::parse_tag(context, attrs)?);
+            context
+                .templates
+                .insert(context.source.clone(), template.clone());
+            return Ok(template);
+        })
+    }
+
+    pub fn parse_tag(
+        context: &mut ParseContext,
+        attrs: &[xa::OwnedAttribute],
+    ) -> Fallible<Template> {
+        parse_tag! {
+            context; attrs;
+            <template>
+                <tileset> => MapTileset::parse_tag,
+                <object> => Object::parse_tag,
+            </template>
+        }
+
+        let source = context.source.clone();
+        let tileset = tileset.pop();
+        let object = object
+            .pop()
+            .ok_or_else(|| err_msg("<template> is missing <object> tag"))?;
+
+        Ok(Template {
+            source,
+            tileset,
+            object,
+        })
+    }
+}
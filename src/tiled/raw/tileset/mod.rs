@@ -1,4 +1,6 @@
 use self::offset::TileOffset;
+use self::terrain::Terrain;
+use self::wang::WangSet;
 use crate::tiled::raw::context::ParseContext;
 use crate::tiled::raw::image::Image;
 use crate::tiled::raw::GlobalTileId;
@@ -10,8 +12,10 @@ use failure::Fallible;
 use xml::attribute as xa;
 
 pub mod offset;
+pub mod terrain;
 pub mod tile;
 pub mod animation;
+pub mod wang;
 
 #[derive(Clone, Debug)]
 pub struct MapTileset {
@@ -55,6 +59,8 @@ pub struct Tileset {
     pub offset: TileOffset,
     pub image: Option<Image>,
     pub tiles: Vec<tile::Tile>,
+    pub terraintypes: Vec<Terrain>,
+    pub wangsets: Vec<WangSet>,
 }
 
 impl Tileset {
@@ -90,6 +96,8 @@ impl Tileset {
                 <tileoffset> => TileOffset::parse_tag,
                 <image> => Image::parse_tag,
                 <tile> => tile::Tile::parse_tag,
+                <terraintypes> => terrain::parse_terraintypes,
+                <wangsets> => wang::parse_wangsets,
             </tileset>
         }
 
@@ -99,6 +107,8 @@ impl Tileset {
         let offset = tileoffset.first().cloned().unwrap_or_default();
         let image = image.first().cloned();
         let tiles = tile;
+        let terraintypes = terraintypes.into_iter().flatten().collect();
+        let wangsets = wangsets.into_iter().flatten().collect();
 
         Ok(Tileset {
             name,
@@ -111,6 +121,8 @@ impl Tileset {
             offset,
             image,
             tiles,
+            terraintypes,
+            wangsets,
         })
     }
 }
\ No newline at end of file
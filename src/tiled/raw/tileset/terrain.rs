@@ -0,0 +1,49 @@
+use crate::tiled::raw::context::ParseContext;
+use crate::tiled::raw::properties::Properties;
+
+use failure::Fallible;
+use xml::attribute as xa;
+
+/// Parses the `<terraintypes>` wrapper tag
+pub fn parse_terraintypes(
+    context: &mut ParseContext,
+    attrs: &[xa::OwnedAttribute],
+) -> Fallible<Vec<Terrain>> {
+    parse_tag! {
+        context; attrs;
+        <terraintypes>
+            <terrain> => Terrain::parse_tag,
+        </terraintypes>
+    }
+
+    Ok(terrain)
+}
+
+/// A terrain type from Tiled 1.4 and earlier. Newer versions save these as corner Wang sets.
+#[derive(Debug)]
+pub struct Terrain {
+    pub name: String,
+    /// The tile shown for the terrain in Tiled, -1 for none
+    pub tile: i32,
+    pub properties: Properties,
+}
+
+impl Terrain {
+    pub fn parse_tag(
+        context: &mut ParseContext,
+        attrs: &[xa::OwnedAttribute],
+    ) -> Fallible<Terrain> {
+        parse_tag! {
+            context; attrs;
+            <terrain name="name"(String) ?tile="tile"(i32)>
+                <properties> => Properties::parse_tag,
+            </terrain>
+        }
+
+        Ok(Terrain {
+            name,
+            tile: tile.unwrap_or(-1),
+            properties: properties.pop().unwrap_or_default(),
+        })
+    }
+}
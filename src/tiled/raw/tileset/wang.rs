@@ -0,0 +1,246 @@
+use crate::tiled::raw::context::ParseContext;
+use crate::tiled::raw::properties::Properties;
+use crate::tiled::raw::LocalTileId;
+
+use failure::{err_msg, Fallible};
+use math2d::Color;
+use xml::attribute as xa;
+
+/// Parses the `<wangsets>` wrapper tag
+pub fn parse_wangsets(
+    context: &mut ParseContext,
+    attrs: &[xa::OwnedAttribute],
+) -> Fallible<Vec<WangSet>> {
+    parse_tag! {
+        context; attrs;
+        <wangsets>
+            <wangset> => WangSet::parse_tag,
+        </wangsets>
+    }
+
+    Ok(wangset)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WangType {
+    Corner,
+    Edge,
+    Mixed,
+}
+
+impl WangType {
+    pub fn parse(s: &str) -> Fallible<WangType> {
+        match s {
+            "corner" => Ok(WangType::Corner),
+            "edge" => Ok(WangType::Edge),
+            "mixed" => Ok(WangType::Mixed),
+            _ => Err(err_msg(format!("Unknown wangset type '{}'", s))),
+        }
+    }
+
+    /// Before Tiled 1.5 the type followed from which kinds of colors the set had
+    pub fn legacy(has_edges: bool, has_corners: bool) -> WangType {
+        match (has_edges, has_corners) {
+            (true, true) => WangType::Mixed,
+            (true, false) => WangType::Edge,
+            _ => WangType::Corner,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WangSet {
+    pub name: String,
+    pub wangtype: WangType,
+    /// The tile shown for the set in Tiled, -1 for none
+    pub tile: i32,
+    pub properties: Properties,
+    pub colors: Vec<WangColor>,
+    pub tiles: Vec<WangTile>,
+}
+
+impl WangSet {
+    pub fn parse_tag(
+        context: &mut ParseContext,
+        attrs: &[xa::OwnedAttribute],
+    ) -> Fallible<WangSet> {
+        parse_tag! {
+            context; attrs;
+            <wangset name="name"(String) ?wangtype="type"(String) ?tile="tile"(i32)>
+                <properties> => Properties::parse_tag,
+                <wangcolor> => WangColor::parse_tag,
+                <wangedgecolor> => WangColor::parse_edge,
+                <wangcornercolor> => WangColor::parse_corner,
+                <wangtile> => WangTile::parse_tag,
+            </wangset>
+        }
+
+        let wangtype = match wangtype {
+            Some(wangtype) => WangType::parse(&wangtype)?,
+            None => WangType::legacy(!wangedgecolor.is_empty(), !wangcornercolor.is_empty()),
+        };
+        let mut tiles = wangtile;
+        let colors = if wangedgecolor.is_empty() && wangcornercolor.is_empty() {
+            wangcolor
+        } else {
+            merge_legacy_colors(wangedgecolor, wangcornercolor, &mut tiles)
+        };
+
+        Ok(WangSet {
+            name,
+            wangtype,
+            tile: tile.unwrap_or(-1),
+            properties: properties.pop().unwrap_or_default(),
+            colors,
+            tiles,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct WangColor {
+    pub name: String,
+    pub color: Color,
+    /// The tile shown for the color in Tiled, -1 for none
+    pub tile: i32,
+    pub probability: f32,
+    pub properties: Properties,
+}
+
+impl WangColor {
+    pub fn parse_tag(
+        context: &mut ParseContext,
+        attrs: &[xa::OwnedAttribute],
+    ) -> Fallible<WangColor> {
+        parse_tag! {
+            context; attrs;
+            <wangcolor
+                name="name"(String) color="color"(String)
+                ?tile="tile"(i32) ?probability="probability"(f32)>
+                <properties> => Properties::parse_tag,
+            </wangcolor>
+        }
+
+        Ok(WangColor {
+            name,
+            color: Color::from_str_argb(&color)?,
+            tile: tile.unwrap_or(-1),
+            probability: probability.unwrap_or(1.0),
+            properties: properties.pop().unwrap_or_default(),
+        })
+    }
+
+    /// `<wangedgecolor>` from Tiled 1.4 and earlier
+    pub fn parse_edge(
+        context: &mut ParseContext,
+        attrs: &[xa::OwnedAttribute],
+    ) -> Fallible<WangColor> {
+        parse_tag! {
+            context; attrs;
+            <wangedgecolor
+                name="name"(String) color="color"(String)
+                ?tile="tile"(i32) ?probability="probability"(f32)/>
+        }
+
+        Self::legacy(name, &color, tile, probability)
+    }
+
+    /// `<wangcornercolor>` from Tiled 1.4 and earlier
+    pub fn parse_corner(
+        context: &mut ParseContext,
+        attrs: &[xa::OwnedAttribute],
+    ) -> Fallible<WangColor> {
+        parse_tag! {
+            context; attrs;
+            <wangcornercolor
+                name="name"(String) color="color"(String)
+                ?tile="tile"(i32) ?probability="probability"(f32)/>
+        }
+
+        Self::legacy(name, &color, tile, probability)
+    }
+
+    fn legacy(
+        name: String,
+        color: &str,
+        tile: Option<i32>,
+        probability: Option<f32>,
+    ) -> Fallible<WangColor> {
+        Ok(WangColor {
+            name,
+            color: Color::from_str_argb(color)?,
+            tile: tile.unwrap_or(-1),
+            probability: probability.unwrap_or(1.0),
+            properties: Properties::default(),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct WangTile {
+    pub tileid: LocalTileId,
+    /// Color index (1-based, 0 for none) of the top edge, top-right corner, right edge,
+    /// bottom-right corner, bottom edge, bottom-left corner, left edge and top-left corner
+    pub wangid: [u8; 8],
+}
+
+impl WangTile {
+    pub fn parse_tag(
+        context: &mut ParseContext,
+        attrs: &[xa::OwnedAttribute],
+    ) -> Fallible<WangTile> {
+        parse_tag! {
+            context; attrs;
+            <wangtile tileid="tileid"(LocalTileId) wangid="wangid"(String)/>
+        }
+
+        Ok(WangTile {
+            tileid,
+            wangid: parse_wangid(&wangid)?,
+        })
+    }
+}
+
+/// Parses either the comma separated ids from Tiled 1.5+ or the older hex format that packs
+/// one id per nibble, in the same order, starting from the low bits
+pub fn parse_wangid(s: &str) -> Fallible<[u8; 8]> {
+    let bad_wangid = || err_msg(format!("Invalid wangid '{}'", s));
+
+    let mut wangid = [0; 8];
+    if s.starts_with("0x") {
+        let value = u32::from_str_radix(&s[2..], 16).map_err(|_| bad_wangid())?;
+        for (i, id) in wangid.iter_mut().enumerate() {
+            *id = (value >> (i * 4) & 0xF) as u8;
+        }
+    } else {
+        let ids = s
+            .split(',')
+            .map(|id| id.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| bad_wangid())?;
+        if ids.len() != 8 {
+            return Err(bad_wangid());
+        }
+        wangid.copy_from_slice(&ids);
+    }
+    Ok(wangid)
+}
+
+/// Sets from before Tiled 1.5 numbered edge and corner colors separately. They're merged into
+/// one list with the corner colors after the edge colors, and the tiles' corner ids shifted
+/// to match.
+pub fn merge_legacy_colors(
+    edge_colors: Vec<WangColor>,
+    corner_colors: Vec<WangColor>,
+    tiles: &mut [WangTile],
+) -> Vec<WangColor> {
+    let edge_count = edge_colors.len() as u8;
+    for tile in tiles.iter_mut() {
+        for corner in tile.wangid.iter_mut().skip(1).step_by(2) {
+            if *corner != 0 {
+                *corner += edge_count;
+            }
+        }
+    }
+    edge_colors.into_iter().chain(corner_colors).collect()
+}
@@ -0,0 +1,117 @@
+use self::pack::Pack;
+use crate::assets::vfs;
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible, ResultExt};
+
+pub mod pack;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Source {
+    File(PathBuf),
+    /// A file inside a pack, by its path relative to the pack's root
+    Packed(Arc<Pack>, Box<str>),
+}
+
+impl Source {
+    /// Files inside one of the asset filesystem's layers are looked up through it, so a higher
+    /// priority layer can replace them. Anything else is read from disk as is. Missing files
+    /// only fail once they're read.
+    pub fn new_file(file: impl AsRef<Path>) -> Source {
+        let file = file.as_ref();
+        let vfs = vfs::current();
+        if let Some(source) = vfs.asset_path_of(file).and_then(|path| vfs.find(&path)) {
+            return source;
+        }
+        match dunce::canonicalize(file) {
+            Ok(file) => Source::File(file),
+            Err(_) => Source::File(absolute(file)),
+        }
+    }
+
+    /// An asset by its path relative to the assets root, e.g. `maps/arena.tmx`. See
+    /// `assets::vfs`.
+    pub fn asset(path: &str) -> Source {
+        match vfs::current().find(path) {
+            Some(source) => source,
+            None => Source::new_file(Path::new("assets").join(path)),
+        }
+    }
+
+    pub fn relative(&self, rel: &str) -> Source {
+        match self {
+            Source::File(file) => Source::new_file(file.with_file_name(rel)),
+            Source::Packed(pack, path) => {
+                let path = Path::new(&**path).with_file_name(rel);
+                match vfs::asset_path(&path) {
+                    Some(path) => vfs::current()
+                        .find(&path)
+                        .unwrap_or_else(|| Source::Packed(pack.clone(), path.into())),
+                    None => Source::new_file(Path::new(pack.root()).join(path)),
+                }
+            }
+        }
+    }
+
+    /// The file's extension, e.g. `tmx`
+    pub fn extension(&self) -> Option<&str> {
+        match self {
+            Source::File(path) => path.extension()?.to_str(),
+            Source::Packed(_, path) => Path::new(&**path).extension()?.to_str(),
+        }
+    }
+
+    pub fn read_all(&self) -> Fallible<Arc<[u8]>> {
+        match self {
+            Source::File(path) => {
+                let data =
+                    std::fs::read(path).with_context(|_| format!("Reading {}", path.display()))?;
+                Ok(data.into())
+            }
+            Source::Packed(pack, path) => {
+                let data = pack.read(path)?.ok_or_else(|| {
+                    err_msg(format!("{} is not in {}", path, pack.path().display()))
+                })?;
+                Ok(data.into())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Source::File(path) => std::fmt::Display::fmt(&path.to_string_lossy(), fmt),
+            Source::Packed(pack, path) => write!(fmt, "{}:{}", pack.path().display(), path),
+        }
+    }
+}
+
+/// Makes `path` absolute without touching the filesystem beyond looking up the working
+/// directory, for files that don't exist so can't be canonicalized
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    let path = match std::env::current_dir() {
+        Ok(cwd) => cwd.join(path),
+        Err(_) => path.to_owned(),
+    };
+    normalize(&path)
+}
+
+/// Resolves `.` and `..` components lexically
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normal.pop() {
+                    normal.push("..");
+                }
+            }
+            component => normal.push(component),
+        }
+    }
+    normal
+}
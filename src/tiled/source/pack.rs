@@ -0,0 +1,209 @@
+//! Pack files bundle the game's assets into one indexed file for release builds:
+//!
+//! | bytes | contents                                                           |
+//! |-------|--------------------------------------------------------------------|
+//! | 8     | `MAGIC`                                                            |
+//! | 4     | format version (u32 LE)                                            |
+//! | 2 + n | root the files were packed from, e.g. `assets` (u16 LE len, utf8)  |
+//! | 4     | number of files (u32 LE)                                           |
+//! | ...   | index, see below                                                   |
+//! | rest  | file contents                                                      |
+//!
+//! Each index entry is the file's path relative to the root (u16 LE len, utf8, `/` separated),
+//! its compression (u8, see `Compression`), the absolute offset of its contents (u64 LE), and
+//! its size before and after compression (u32 LE each).
+//!
+//! Packs are used as layers of the asset filesystem, see `assets::vfs`.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use failure::{err_msg, Fallible, ResultExt};
+
+pub const MAGIC: &[u8; 8] = b"ARENAPAK";
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub struct Pack {
+    path: PathBuf,
+    root: String,
+    entries: HashMap<Box<str>, Entry>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+    compression: Compression,
+    offset: u64,
+    size: u32,
+    packed_size: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Compression {
+    Stored = 0,
+    Zstd = 1,
+}
+
+impl Pack {
+    pub fn open(path: impl AsRef<Path>) -> Fallible<Pack> {
+        let path = path.as_ref();
+        let file =
+            std::fs::File::open(path).with_context(|_| format!("Opening {}", path.display()))?;
+        let mut reader = std::io::BufReader::new(file);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(err_msg(format!("{} is not a pack file", path.display())));
+        }
+        let version = reader.read_u32::<LE>()?;
+        if version != FORMAT_VERSION {
+            return Err(err_msg(format!(
+                "Pack format version {} is not supported, expected {}",
+                version, FORMAT_VERSION
+            )));
+        }
+
+        let root = read_string(&mut reader).context("Reading pack root")?;
+        let count = reader.read_u32::<LE>()?;
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let name = read_string(&mut reader).context("Reading pack index")?;
+            let compression = match reader.read_u8()? {
+                0 => Compression::Stored,
+                1 => Compression::Zstd,
+                c => return Err(err_msg(format!("Unknown compression {} for {}", c, name))),
+            };
+            let entry = Entry {
+                compression,
+                offset: reader.read_u64::<LE>()?,
+                size: reader.read_u32::<LE>()?,
+                packed_size: reader.read_u32::<LE>()?,
+            };
+            entries.insert(name.into_boxed_str(), entry);
+        }
+
+        Ok(Pack {
+            path: dunce::canonicalize(path).unwrap_or_else(|_| path.to_owned()),
+            root,
+            entries,
+        })
+    }
+
+    /// The pack file itself
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The directory the files were packed from, relative to the working directory
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Uncompressed size of a file in the pack
+    pub fn size(&self, name: &str) -> Option<u64> {
+        self.entries.get(name).map(|entry| entry.size as u64)
+    }
+
+    /// Every file in the pack, in no particular order
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|name| &**name)
+    }
+
+    /// Reads and decompresses a file, or returns None if the pack doesn't contain it
+    pub fn read(&self, name: &str) -> Fallible<Option<Vec<u8>>> {
+        let entry = match self.entries.get(name) {
+            Some(entry) => *entry,
+            None => return Ok(None),
+        };
+        let context = || format!("Reading {} from {}", name, self.path.display());
+
+        let mut file = std::fs::File::open(&self.path).with_context(|_| context())?;
+        file.seek(SeekFrom::Start(entry.offset))
+            .with_context(|_| context())?;
+        let mut packed = vec![0; entry.packed_size as usize];
+        file.read_exact(&mut packed).with_context(|_| context())?;
+
+        let data = match entry.compression {
+            Compression::Stored => packed,
+            Compression::Zstd => zstd::decode_all(&packed[..]).with_context(|_| context())?,
+        };
+        if data.len() != entry.size as usize {
+            return Err(err_msg(format!("{} is corrupt", context())));
+        }
+        Ok(Some(data))
+    }
+
+    /// Writes a pack containing `files`, given as paths relative to `root` with their contents.
+    /// Files are compressed unless that doesn't make them smaller.
+    pub fn write(mut writer: impl Write, root: &str, files: &[(String, Vec<u8>)]) -> Fallible<()> {
+        let mut packed = Vec::with_capacity(files.len());
+        for (name, data) in files.iter() {
+            let compressed = zstd::encode_all(&data[..], 19)
+                .with_context(|_| format!("Compressing {}", name))?;
+            if compressed.len() < data.len() {
+                packed.push((Compression::Zstd, compressed));
+            } else {
+                packed.push((Compression::Stored, data.clone()));
+            }
+        }
+
+        let index_len: usize = files.iter().map(|(name, _)| 2 + name.len() + 17).sum();
+        let mut offset = (8 + 4 + 2 + root.len() + 4 + index_len) as u64;
+
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LE>(FORMAT_VERSION)?;
+        write_string(&mut writer, root)?;
+        writer.write_u32::<LE>(files.len() as u32)?;
+        for ((name, data), (compression, contents)) in files.iter().zip(packed.iter()) {
+            write_string(&mut writer, name)?;
+            writer.write_u8(*compression as u8)?;
+            writer.write_u64::<LE>(offset)?;
+            writer.write_u32::<LE>(data.len() as u32)?;
+            writer.write_u32::<LE>(contents.len() as u32)?;
+            offset += contents.len() as u64;
+        }
+        for (_, contents) in packed.iter() {
+            writer.write_all(contents)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Packs are identified by their file, so sources from the same pack compare equal
+impl PartialEq for Pack {
+    fn eq(&self, other: &Pack) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for Pack {}
+
+impl std::hash::Hash for Pack {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.path.hash(state)
+    }
+}
+
+fn read_string(reader: &mut impl Read) -> Fallible<String> {
+    let len = reader.read_u16::<LE>()? as usize;
+    let mut data = vec![0; len];
+    reader.read_exact(&mut data)?;
+    Ok(String::from_utf8(data)?)
+}
+
+fn write_string(writer: &mut impl Write, s: &str) -> Fallible<()> {
+    if s.len() > u16::max_value() as usize {
+        return Err(err_msg(format!("Path is too long to pack: {}", s)));
+    }
+    writer.write_u16::<LE>(s.len() as u16)?;
+    writer.write_all(s.as_bytes())?;
+    Ok(())
+}
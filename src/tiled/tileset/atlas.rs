@@ -0,0 +1,58 @@
+use crate::tiled::tileset::cache;
+use crate::tiled::tileset::image::Image;
+
+use failure::{err_msg, Fallible};
+use image::RgbaImage;
+use math2d::Recti;
+
+/// Empty pixels left between packed images so filtering doesn't bleed between neighbours
+const PADDING: u32 = 1;
+
+/// Packs the images of an image-collection tileset into a single atlas image using simple
+/// shelf packing. Returns the atlas and the pixel rect of each input image, in the same order.
+pub fn pack(images: &[RgbaImage]) -> Fallible<(Image, Vec<Recti>)> {
+    if images.is_empty() {
+        return Err(err_msg("Image collection tileset has no images"));
+    }
+
+    let area: u32 = images
+        .iter()
+        .map(|img| (img.width() + PADDING) * (img.height() + PADDING))
+        .sum();
+    let widest = images.iter().map(|img| img.width()).max().unwrap_or(0);
+    let atlas_width = ((area as f32).sqrt().ceil() as u32)
+        .max(widest)
+        .next_power_of_two();
+
+    // Tallest first keeps the shelves reasonably full
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(images[i].height()));
+
+    let mut rects = vec![Recti::new(0, 0, 0, 0); images.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for &i in order.iter() {
+        let (w, h) = images[i].dimensions();
+        if x + w > atlas_width {
+            x = 0;
+            y += shelf_height + PADDING;
+            shelf_height = 0;
+        }
+        rects[i] = Recti::new(x as i32, y as i32, (x + w) as i32, (y + h) as i32);
+        x += w + PADDING;
+        shelf_height = shelf_height.max(h);
+    }
+    let atlas_height = y + shelf_height;
+
+    if atlas_width > u16::max_value() as u32 || atlas_height > u16::max_value() as u32 {
+        return Err(err_msg("Image collection is too large to fit in an atlas"));
+    }
+
+    let mut atlas = RgbaImage::new(atlas_width, atlas_height);
+    for (img, rect) in images.iter().zip(rects.iter()) {
+        for (px, py, pixel) in img.enumerate_pixels() {
+            atlas.put_pixel(rect.left as u32 + px, rect.top as u32 + py, *pixel);
+        }
+    }
+
+    Ok((cache::encode(atlas)?, rects))
+}
@@ -0,0 +1,209 @@
+//! Caches images encoded to WebP, since encoding is by far the slowest part of loading a map.
+//!
+//! Image files are remembered in memory by their `Source` for as long as the file (or the pack
+//! it's in) isn't modified, so maps sharing a tileset (or the same map being reloaded) don't
+//! even read it again. Below that is an on-disk cache keyed by a hash of the file's contents,
+//! which survives restarts and catches files that were touched without being changed.
+//!
+//! The cache is shared by the whole process, so maps decoding on the asset manager's workers
+//! share images too. Failing to write to the disk cache is never an error.
+
+use crate::tiled::binmap::content_hash;
+use crate::tiled::raw;
+use crate::tiled::source::Source;
+use crate::tiled::tileset::image::Image;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use byteorder::{ByteOrder, LE};
+use failure::{Fallible, ResultExt};
+
+/// Bump if `Image::from_image` changes how it encodes, so stale entries are ignored
+const CACHE_VERSION: u32 = 1;
+
+lazy_static! {
+    static ref CACHE: Mutex<Cache> = Mutex::new(Cache {
+        dir: default_dir(),
+        memory: HashMap::new(),
+    });
+}
+
+struct Cache {
+    dir: Option<PathBuf>,
+    memory: HashMap<MemoryKey, MemoryEntry>,
+}
+
+type MemoryKey = (Source, Option<(u8, u8, u8)>);
+
+struct MemoryEntry {
+    stamp: FileStamp,
+    image: Encoded,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+#[derive(Clone)]
+struct Encoded {
+    data: Box<[u8]>,
+    width: u16,
+    height: u16,
+}
+
+/// Where the disk cache lives. Defaults to `$ARENA_WEBP_CACHE`, or a directory in the system's
+/// temp dir if that isn't set. Setting the variable to an empty string disables the disk cache.
+pub fn set_dir(dir: Option<PathBuf>) {
+    cache().dir = dir;
+}
+
+/// Forgets every image remembered in memory. Images cached on disk are kept.
+pub fn clear_memory() {
+    cache().memory.clear();
+}
+
+/// Loads the image referenced by an image tag, encoding it only if it isn't cached
+pub fn load(raw: &raw::Image) -> Fallible<Image> {
+    let trans = raw.transparent.map(|raw::TransColor(r, g, b)| (r, g, b));
+    let key = (raw.source.clone(), trans);
+    let stamp = file_stamp(&raw.source);
+
+    if let Some(stamp) = stamp {
+        let hit = match cache().memory.get(&key) {
+            Some(entry) if entry.stamp == stamp => Some(entry.image.clone()),
+            _ => None,
+        };
+        if let Some(image) = hit {
+            return Ok(image.into_image());
+        }
+    }
+
+    let data = raw.source.read_all().context("Reading image file")?;
+    let name = match trans {
+        Some((r, g, b)) => format!("{:016x}-{:02x}{:02x}{:02x}", content_hash(&data), r, g, b),
+        None => format!("{:016x}", content_hash(&data)),
+    };
+    let image = cached_encode(&name, || {
+        let img = Image::decode_rgba(raw, &data)?;
+        Image::from_image(img).context("Formatting image")
+    })?;
+
+    if let Some(stamp) = stamp {
+        let entry = MemoryEntry {
+            stamp,
+            image: image.clone(),
+        };
+        cache().memory.insert(key, entry);
+    }
+    Ok(image.into_image())
+}
+
+/// Encodes pixels that don't come straight from a file, like a packed atlas
+pub fn encode(img: image::RgbaImage) -> Fallible<Image> {
+    let name = format!(
+        "{:016x}-{}x{}-rgba",
+        content_hash(&img),
+        img.width(),
+        img.height()
+    );
+    let image = cached_encode(&name, || Image::from_image(img))?;
+    Ok(image.into_image())
+}
+
+fn cached_encode(name: &str, encode: impl FnOnce() -> Fallible<Image>) -> Fallible<Encoded> {
+    let path = cache()
+        .dir
+        .as_ref()
+        .map(|dir| dir.join(format!("v{}", CACHE_VERSION)).join(name));
+
+    if let Some(image) = path.as_ref().and_then(|path| read_entry(path)) {
+        return Ok(image);
+    }
+
+    let image = encode()?;
+    let image = Encoded {
+        data: image.data,
+        width: image.width,
+        height: image.height,
+    };
+    if let Some(path) = &path {
+        let _ = write_entry(path, &image);
+    }
+    Ok(image)
+}
+
+/// Entries are the width and height (u16 LE) followed by the WebP data
+fn read_entry(path: &Path) -> Option<Encoded> {
+    let data = std::fs::read(path).ok()?;
+    if data.len() < 16 || &data[4..8] != b"RIFF" || &data[12..16] != b"WEBP" {
+        return None;
+    }
+    Some(Encoded {
+        width: LE::read_u16(&data[0..2]),
+        height: LE::read_u16(&data[2..4]),
+        data: data[4..].into(),
+    })
+}
+
+fn write_entry(path: &Path, image: &Encoded) -> Fallible<()> {
+    let mut data = vec![0; 4];
+    LE::write_u16(&mut data[0..2], image.width);
+    LE::write_u16(&mut data[2..4], image.height);
+    data.extend_from_slice(&image.data);
+
+    // Written next to the entry and renamed into place so other processes never see half of it
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temp, &data)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Only held while looking things up, never while reading or encoding, so threads loading
+/// different images don't wait on each other. A panic while holding it can't leave the cache
+/// half updated, so a poisoned lock is still used.
+fn cache() -> MutexGuard<'static, Cache> {
+    CACHE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn file_stamp(source: &Source) -> Option<FileStamp> {
+    match source {
+        Source::File(path) => {
+            let meta = std::fs::metadata(path).ok()?;
+            Some(FileStamp {
+                modified: meta.modified().ok()?,
+                len: meta.len(),
+            })
+        }
+        Source::Packed(pack, path) => {
+            let meta = std::fs::metadata(pack.path()).ok()?;
+            Some(FileStamp {
+                modified: meta.modified().ok()?,
+                len: pack.size(path)?,
+            })
+        }
+    }
+}
+
+fn default_dir() -> Option<PathBuf> {
+    match std::env::var_os("ARENA_WEBP_CACHE") {
+        Some(ref dir) if dir.is_empty() => None,
+        Some(dir) => Some(dir.into()),
+        None => Some(std::env::temp_dir().join("pixel-arena-game").join("webp")),
+    }
+}
+
+impl Encoded {
+    fn into_image(self) -> Image {
+        Image {
+            data: self.data,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
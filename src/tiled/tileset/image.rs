@@ -1,5 +1,6 @@
 use crate::graphics::core::GraphicsCore;
 use crate::tiled::raw;
+use crate::tiled::tileset::cache;
 
 use failure::{Fallible, ResultExt};
 use glium::texture::{RawImage2d, SrgbTexture2d};
@@ -10,32 +11,52 @@ pub struct Image {
     pub data: Box<[u8]>,
     pub width: u16,
     pub height: u16,
-
-    #[serde(skip)]
-    pub(super) texture: Option<SrgbTexture2d>,
 }
 
 impl Image {
     pub fn from_raw(raw: Option<&raw::Image>) -> Fallible<Self> {
         let raw = raw.ok_or_else(|| failure::err_msg("Tileset is missing image tag"))?;
-        let data = raw.source.read_all().context("Reading image file")?;
-        let decoded = ::image::load_from_memory(&data).context("Decoding image data")?;
-        let image = Self::from_image(decoded.to_rgba()).context("Formatting image")?;
+        cache::load(raw)
+    }
 
-        Ok(image)
+    /// Reads and decodes the file referenced by an image tag. Pixels matching the tag's
+    /// transparent color key are made fully transparent.
+    pub fn load_rgba(raw: &raw::Image) -> Fallible<image::RgbaImage> {
+        let data = raw.source.read_all().context("Reading image file")?;
+        Self::decode_rgba(raw, &data)
     }
 
-    pub(super) fn initialize(&mut self, core: &GraphicsCore) -> Fallible<()> {
-        let raw_data = self.decode_raw_image().context("Decoding tileset image")?;
+    /// Like `load_rgba`, for when the file has already been read
+    pub fn decode_rgba(raw: &raw::Image, data: &[u8]) -> Fallible<image::RgbaImage> {
+        let decoded = ::image::load_from_memory(data).context("Decoding image data")?;
+        let mut img = decoded.to_rgba();
+
+        if let Some(raw::TransColor(r, g, b)) = raw.transparent {
+            for pixel in img.pixels_mut() {
+                if pixel[0] == r && pixel[1] == g && pixel[2] == b {
+                    *pixel = ::image::Rgba([0, 0, 0, 0]);
+                }
+            }
+        }
 
-        let dims = (self.width as u32, self.height as u32);
-        let raw_image = RawImage2d::from_raw_rgba(raw_data, dims);
+        Ok(img)
+    }
 
+    /// Uploads pixels decoded with `to_rgba`. Decoding is the slow part, so it can happen on
+    /// another thread while this has to run on the main thread.
+    pub fn create_texture(core: &GraphicsCore, rgba: image::RgbaImage) -> Fallible<SrgbTexture2d> {
+        let dims = rgba.dimensions();
+        let raw_image = RawImage2d::from_raw_rgba(rgba.into_raw(), dims);
         let texture = SrgbTexture2d::new(&core.display, raw_image)
-            .context("Creating tileset image texture buffer")?;
+            .context("Creating image texture buffer")?;
+        Ok(texture)
+    }
 
-        self.texture = Some(texture);
-        Ok(())
+    /// Decodes the stored image back into pixels
+    pub fn to_rgba(&self) -> Fallible<image::RgbaImage> {
+        let data = self.decode_raw_image()?;
+        image::RgbaImage::from_raw(self.width as u32, self.height as u32, data)
+            .ok_or_else(|| failure::err_msg("Image data is invalid"))
     }
 
     pub fn rect(&self, rect: math2d::Recti) -> math2d::Rectf {
@@ -51,6 +72,7 @@ impl Image {
         .into()
     }
 
+    /// Encodes the pixels to lossless WebP. This is slow, prefer `cache::encode`.
     pub fn from_image(img: image::RgbaImage) -> Fallible<Image> {
         let width = img.width() as u16;
         let height = img.height() as u16;
@@ -78,8 +100,6 @@ impl Image {
             data,
             width,
             height,
-
-            texture: None,
         })
     }
 
@@ -2,17 +2,23 @@ use crate::graphics::core::GraphicsCore;
 use crate::tiled::map::LocalTileId;
 use crate::tiled::raw;
 use crate::tiled::tileset::tile::Tile;
+use crate::tiled::tileset::wang::WangSet;
 
 use failure::{err_msg, Fallible, ResultExt};
 use glium::texture::{ClientFormat, RawImage1d, SrgbTexture2d, Texture1d};
-use math2d::Vector2f;
+use math2d::{Recti, Vector2f};
 
+pub mod atlas;
+pub mod cache;
 pub mod image;
 pub mod tile;
+pub mod wang;
 
 #[derive(Serialize, Deserialize)]
 pub struct Tileset {
     pub tile_scale: Vector2f,
+    /// Tiled's `tileoffset` in world units, applied to every tile drawn from this tileset
+    pub offset: Vector2f,
     pub tile_width: u16,
     pub tile_height: u16,
     pub rows: u8,
@@ -20,11 +26,40 @@ pub struct Tileset {
     pub margin: u8,
     pub spacing: u8,
 
+    /// The tileset image, or an atlas packed at load time for image-collection tilesets
     pub image: image::Image,
+    /// Pixel rect of each tile inside `image`
+    pub image_rects: Box<[Recti]>,
     pub tiles: Box<[Tile]>,
+    /// Wang sets for autotiling, including one converted from the tileset's terrains if it
+    /// was made in an older version of Tiled
+    pub wang_sets: Box<[WangSet]>,
 
+    /// The uv rect and world size of every tile in the image
     #[serde(skip)]
-    tile_rect_buffer: Option<Texture1d>,
+    tile_rects: Vec<([f32; 4], Vector2f)>,
+    /// Which tile's rect is currently shown for each tile, so animations can swap frames
+    /// without touching the chunks
+    #[serde(skip)]
+    shown_tiles: Box<[LocalTileId]>,
+}
+
+/// What a tileset needs on the GPU to be drawn, created by `Tileset::upload`. Kept apart from the
+/// tileset so tilesets can be loaded on other threads.
+pub struct TilesetGpu {
+    pub image: SrgbTexture2d,
+    pub rect_buffer: Texture1d,
+}
+
+/// How the tiles of a single-image tileset are laid out in the image
+#[derive(Copy, Clone, Debug)]
+pub struct GridLayout {
+    pub tile_width: u16,
+    pub tile_height: u16,
+    pub columns: u8,
+    pub rows: u8,
+    pub margin: u8,
+    pub spacing: u8,
 }
 
 impl Tileset {
@@ -32,19 +67,58 @@ impl Tileset {
         self.tiles.get(id.0 as usize)
     }
 
+    /// Size of a tile in world units. Tiles from image collections can each have their own size.
+    pub fn tile_size(&self, id: LocalTileId) -> Option<Vector2f> {
+        let rect = self.image_rects.get(id.0 as usize)?;
+        let local_size = Vector2f::new(self.tile_width as f32, self.tile_height as f32);
+        let rect = rect.to_f32();
+        let size = Vector2f::new(rect.right - rect.left, rect.bottom - rect.top);
+        Some(size / local_size * self.tile_scale)
+    }
+
     pub fn from_raw(raw: &raw::Tileset, tile_size: Vector2f) -> Fallible<Tileset> {
+        let is_collection = raw.image.is_none() && raw.tiles.iter().any(|t| t.image.is_some());
+
         let tile_width = raw.tilewidth as u16;
         let tile_height = raw.tileheight as u16;
-        let columns = raw.columns as u8;
-        let rows = ((raw.tilecount + raw.columns - 1) / raw.columns) as u8;
         let margin = raw.margin as u8;
         let spacing = raw.spacing as u8;
         let local_tile_size: Vector2f = [tile_width as f32, tile_height as f32].into();
         let tile_scale = local_tile_size / tile_size;
+        let offset = Vector2f::new(raw.offset.x, -raw.offset.y) / tile_size;
+
+        // Tiles removed from an image collection leave holes in the ids
+        let tile_count = if is_collection {
+            let max_id = raw.tiles.iter().map(|t| t.id.0 + 1).max().unwrap_or(0);
+            max_id.max(raw.tilecount as u32) as usize
+        } else {
+            raw.tilecount as usize
+        };
 
-        let image = image::Image::from_raw(raw.image.as_ref()).context("Loading tileset image")?;
+        let (columns, rows, image, image_rects) = if is_collection {
+            let (image, image_rects) =
+                Self::pack_collection(raw, tile_count).context("Packing image collection")?;
+            (0, 0, image, image_rects)
+        } else {
+            if raw.columns <= 0 {
+                return Err(err_msg("Tileset has no columns"));
+            }
+            let columns = raw.columns as u8;
+            let rows = ((raw.tilecount + raw.columns - 1) / raw.columns) as u8;
+            let image =
+                image::Image::from_raw(raw.image.as_ref()).context("Loading tileset image")?;
+            let image_rects = Self::grid_rects(
+                columns,
+                rows,
+                tile_width,
+                tile_height,
+                margin,
+                spacing,
+            );
+            (columns, rows, image, image_rects)
+        };
 
-        let mut tiles = vec![Tile::default(); raw.tilecount as usize].into_boxed_slice();
+        let mut tiles = vec![Tile::default(); tile_count].into_boxed_slice();
         for rawtile in &raw.tiles {
             if let Some(tile) = tiles.get_mut(rawtile.id.0 as usize) {
                 *tile = Tile::from_raw(rawtile, tile_size)?;
@@ -53,8 +127,19 @@ impl Tileset {
             }
         }
 
+        let mut wang_sets = raw
+            .wangsets
+            .iter()
+            .map(|set| WangSet::from_raw(set, &raw.tiles))
+            .collect::<Fallible<Vec<_>>>()
+            .context("Loading wang sets")?;
+        if let Some(terrains) = WangSet::from_terrains(&raw.terraintypes, &raw.tiles)? {
+            wang_sets.push(terrains);
+        }
+
         let tileset = Tileset {
             tile_scale,
+            offset,
             tile_width,
             tile_height,
             columns,
@@ -62,9 +147,12 @@ impl Tileset {
             margin,
             spacing,
             image,
+            image_rects,
             tiles,
+            wang_sets: wang_sets.into_boxed_slice(),
 
-            tile_rect_buffer: None,
+            tile_rects: Vec::new(),
+            shown_tiles: Default::default(),
         };
 
         tileset
@@ -74,6 +162,97 @@ impl Tileset {
         Ok(tileset)
     }
 
+    /// Assembles a single-image tileset from already loaded parts, e.g. when upgrading an old
+    /// binmap. Everything that isn't passed in starts out empty.
+    pub(crate) fn from_grid(
+        tile_scale: Vector2f,
+        grid: GridLayout,
+        image: image::Image,
+        tiles: Box<[Tile]>,
+    ) -> Fallible<Tileset> {
+        let GridLayout {
+            tile_width,
+            tile_height,
+            columns,
+            rows,
+            margin,
+            spacing,
+        } = grid;
+        let image_rects = Self::grid_rects(columns, rows, tile_width, tile_height, margin, spacing);
+
+        let tileset = Tileset {
+            tile_scale,
+            offset: Vector2f::new(0.0, 0.0),
+            tile_width,
+            tile_height,
+            columns,
+            rows,
+            margin,
+            spacing,
+            image,
+            image_rects,
+            tiles,
+            wang_sets: Default::default(),
+
+            tile_rects: Vec::new(),
+            shown_tiles: Default::default(),
+        };
+        tileset.validate()?;
+        Ok(tileset)
+    }
+
+    fn grid_rects(
+        columns: u8,
+        rows: u8,
+        tile_width: u16,
+        tile_height: u16,
+        margin: u8,
+        spacing: u8,
+    ) -> Box<[Recti]> {
+        let tw = tile_width as i32;
+        let th = tile_height as i32;
+        let x0 = margin as i32;
+        let y0 = margin as i32;
+        let xs = tw + spacing as i32;
+        let ys = th + spacing as i32;
+
+        let mut rects = Vec::with_capacity(rows as usize * columns as usize);
+        for ty in 0..rows as i32 {
+            for tx in 0..columns as i32 {
+                let x = x0 + tx * xs;
+                let y = y0 + ty * ys;
+                rects.push(Recti::new(x, y, x + tw, y + th));
+            }
+        }
+        rects.into_boxed_slice()
+    }
+
+    fn pack_collection(
+        raw: &raw::Tileset,
+        tile_count: usize,
+    ) -> Fallible<(image::Image, Box<[Recti]>)> {
+        let mut ids = Vec::new();
+        let mut images = Vec::new();
+        for rawtile in raw.tiles.iter() {
+            if let Some(rawimage) = &rawtile.image {
+                let img = image::Image::load_rgba(rawimage)
+                    .with_context(|_| format!("Loading image for tile {}", rawtile.id.0))?;
+                ids.push(rawtile.id.0 as usize);
+                images.push(img);
+            }
+        }
+
+        let (atlas, packed) = atlas::pack(&images)?;
+
+        // Ids without an image get an empty rect
+        let mut rects = vec![Recti::new(0, 0, 0, 0); tile_count];
+        for (id, rect) in ids.into_iter().zip(packed) {
+            rects[id] = rect;
+        }
+
+        Ok((atlas, rects.into_boxed_slice()))
+    }
+
     pub fn validate(&self) -> Fallible<()> {
         let cols = self.columns as usize;
         let rows = self.rows as usize;
@@ -81,6 +260,32 @@ impl Tileset {
         let twidth = self.tile_width as usize + self.spacing as usize;
         let theight = self.tile_height as usize + self.spacing as usize;
 
+        for tile in self.tiles.iter() {
+            if tile.animation.iter().any(|f| f.tile.0 as usize >= self.tiles.len()) {
+                return Err(err_msg("Tile animation references a tile outside the tileset"));
+            }
+        }
+        for set in self.wang_sets.iter() {
+            if set.tiles.iter().any(|t| t.tile.0 as usize >= self.tiles.len()) {
+                return Err(err_msg("Wang set references a tile outside the tileset"));
+            }
+        }
+
+        // Image collections have no grid layout, just one rect per tile
+        if cols == 0 {
+            if self.image_rects.len() != self.tiles.len() {
+                return Err(err_msg("Image collection has a rect count mismatch"));
+            }
+            let width = self.image.width as i32;
+            let height = self.image.height as i32;
+            for rect in self.image_rects.iter() {
+                if rect.left < 0 || rect.top < 0 || rect.right > width || rect.bottom > height {
+                    return Err(err_msg("Image collection rect is outside of the atlas"));
+                }
+            }
+            return Ok(());
+        }
+
         let max_count = rows * cols;
         let min_count = max_count - cols + 1;
         if self.tiles.len() < min_count || self.tiles.len() > max_count {
@@ -98,51 +303,67 @@ impl Tileset {
         Ok(())
     }
 
-    pub fn initialize(&mut self, core: &GraphicsCore) -> Fallible<()> {
-        self.image
-            .initialize(core)
-            .context("Initializing tileset image")?;
-
-        self.create_rect_buffer(core)
+    /// Uploads the tileset image, given its pixels from `image.to_rgba()`, and creates the rect
+    /// buffer
+    pub fn upload(
+        &mut self,
+        core: &GraphicsCore,
+        rgba: ::image::RgbaImage,
+    ) -> Fallible<TilesetGpu> {
+        let image =
+            image::Image::create_texture(core, rgba).context("Initializing tileset image")?;
+        let rect_buffer = self
+            .create_rect_buffer(core)
             .context("Initializing tileset rect buffer")?;
 
-        Ok(())
+        Ok(TilesetGpu { image, rect_buffer })
     }
 
-    pub fn tileset_image(&self) -> &SrgbTexture2d {
-        self.image
-            .texture
-            .as_ref()
-            .expect("Tileset must be initialized after loading")
-    }
+    /// Swaps animated tiles over to their current frame. Only the texels of tiles whose frame
+    /// actually changed are written to the rect buffer.
+    pub fn update_animations(&mut self, gpu: &TilesetGpu, time: f64) -> Fallible<()> {
+        let time_ms = (time * 1000.0) as u64;
+        for (i, tile) in self.tiles.iter().enumerate() {
+            let frame = match tile.current_frame(time_ms) {
+                Some(frame) if self.shown_tiles[i] != frame => frame,
+                _ => continue,
+            };
+            self.shown_tiles[i] = frame;
 
-    pub fn tile_rect_buffer(&self) -> &Texture1d {
-        self.tile_rect_buffer
-            .as_ref()
-            .expect("Tileset must be initialized after loading")
-    }
+            let texels = Self::rect_texels(&self.tile_rects, &self.shown_tiles, i);
+            let raw = RawImage1d {
+                format: ClientFormat::F32F32F32F32,
+                width: 2,
+                data: texels[..].into(),
+            };
+            let rect = glium::Rect {
+                left: i as u32 * 2,
+                bottom: 0,
+                width: 2,
+                height: 1,
+            };
+            gpu.rect_buffer.write(rect, raw);
+        }
 
-    fn create_rect_buffer(&mut self, core: &GraphicsCore) -> Fallible<()> {
-        let mut buf = Vec::with_capacity(self.tiles.len() * 4);
+        Ok(())
+    }
 
-        let tw = self.tile_width as i32;
-        let th = self.tile_height as i32;
-        let x0 = self.margin as i32;
-        let y0 = self.margin as i32;
-        let xs = tw + self.spacing as i32;
-        let ys = th + self.spacing as i32;
+    fn create_rect_buffer(&mut self, core: &GraphicsCore) -> Fallible<Texture1d> {
+        let rects = (0..self.image_rects.len())
+            .map(|i| {
+                let rect = self.image.rect(self.image_rects[i]);
+                let size = self.tile_size(LocalTileId(i as u16)).unwrap_or(self.tile_scale);
+                ([rect.left, rect.top, rect.right, rect.bottom], size)
+            })
+            .collect();
+        self.tile_rects = rects;
+        self.shown_tiles = (0..self.tiles.len())
+            .map(|i| LocalTileId(i as u16))
+            .collect();
 
-        for ty in 0..self.rows as i32 {
-            for tx in 0..self.columns as i32 {
-                let x = x0 + tx * xs;
-                let y = y0 + ty * ys;
-                let irect = math2d::Recti::new(x, y, x + tw, y + th);
-                let rect = self.image.rect(irect);
-                buf.push(rect.left);
-                buf.push(rect.top);
-                buf.push(rect.right);
-                buf.push(rect.bottom);
-            }
+        let mut buf = Vec::with_capacity(self.tile_rects.len() * 8);
+        for i in 0..self.tile_rects.len() {
+            buf.extend_from_slice(&Self::rect_texels(&self.tile_rects, &self.shown_tiles, i));
         }
 
         let raw = RawImage1d {
@@ -151,9 +372,18 @@ impl Tileset {
             data: buf.into(),
         };
 
-        let tex = Texture1d::new(&core.display, raw)?;
-        self.tile_rect_buffer = Some(tex);
+        Ok(Texture1d::new(&core.display, raw)?)
+    }
 
-        Ok(())
+    /// The two texels of tile `i` in the rect buffer, see tile_chunk.vert: the uv rect of the
+    /// frame it currently shows, then its world size
+    fn rect_texels(
+        tile_rects: &[([f32; 4], Vector2f)],
+        shown_tiles: &[LocalTileId],
+        i: usize,
+    ) -> [f32; 8] {
+        let shown = shown_tiles.get(i).map(|t| t.0 as usize).unwrap_or(i);
+        let (rect, size) = tile_rects.get(shown).unwrap_or(&tile_rects[i]);
+        [rect[0], rect[1], rect[2], rect[3], size.x, size.y, 0.0, 0.0]
     }
 }
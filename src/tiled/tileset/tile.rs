@@ -1,13 +1,18 @@
 use crate::physics::MetaBody;
+use crate::tiled::map::properties::Properties;
+use crate::tiled::map::LocalTileId;
 use crate::tiled::raw;
 
 use failure::{err_msg, Fallible};
-use math2d::{Point2f, Vector2f};
+use math2d::{Matrix3x2f, Point2f, Vector2f};
 
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Tile {
     pub flags: TileFlags,
     pub colliders: Box<[Collider]>,
+    /// Empty if the tile isn't animated
+    pub animation: Box<[Frame]>,
+    pub properties: Properties,
 }
 
 impl Tile {
@@ -26,20 +31,54 @@ impl Tile {
         }
         let colliders = colliders.into_boxed_slice();
 
-        Ok(Tile { flags, colliders })
+        let animation = raw
+            .animation
+            .as_ref()
+            .map(|anim| &anim.frames[..])
+            .unwrap_or(&[])
+            .iter()
+            .map(Frame::from_raw)
+            .collect();
+
+        let properties = Properties::from_raw(&raw.properties);
+
+        Ok(Tile {
+            flags,
+            colliders,
+            animation,
+            properties,
+        })
+    }
+
+    /// The tile that should be displayed in place of this one `time_ms` milliseconds into the
+    /// animation. Returns None for tiles that aren't animated.
+    pub fn current_frame(&self, time_ms: u64) -> Option<LocalTileId> {
+        let total: u64 = self.animation.iter().map(|f| f.duration as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut t = time_ms % total;
+        for frame in self.animation.iter() {
+            if t < frame.duration as u64 {
+                return Some(frame.tile);
+            }
+            t -= frame.duration as u64;
+        }
+        None
     }
 
-    pub fn create_collider(&self, pos: &Point2f, body: &mut MetaBody) {
+    /// Creates the fixtures for this tile's colliders. `transform` maps tile-local coordinates
+    /// into the body's space, including any flipping applied to the tile instance.
+    pub fn create_collider(&self, transform: &Matrix3x2f, body: &mut MetaBody) {
         use std::f32::consts::PI;
 
         use approx::ulps_ne;
         use math2d::{Matrix3x2f as M, RectCorner::*};
         use wrapped2d::b2;
 
-        let pos = pos.to_vector();
-
         for collider in self.colliders.iter() {
-            let mat = M::rotation(-collider.rotation, collider.origin) * M::translation(pos);
+            let mat = M::rotation(-collider.rotation, collider.origin) * *transform;
 
             let mut def = b2::FixtureDef::new();
             def.is_sensor = collider.flags.is_sensor();
@@ -118,6 +157,21 @@ impl Tile {
     }
 }
 
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub tile: LocalTileId,
+    /// How long the frame is shown for in milliseconds
+    pub duration: u32,
+}
+
+impl Frame {
+    pub fn from_raw(raw: &raw::Frame) -> Self {
+        let tile = LocalTileId(raw.tileid.0 as u16);
+        let duration = raw.duration.as_secs() as u32 * 1000 + raw.duration.subsec_millis();
+        Frame { tile, duration }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Collider {
     pub shape: Shape,
@@ -211,31 +265,48 @@ pub enum TileFlags {
     NONE = 0,
 }
 
+const TILE_FLAG_NAMES: &[(&str, TileFlags)] = &[
+    ("NULL", TileFlags::NULL),
+    ("WALL", TileFlags::WALL),
+    ("LADDER", TileFlags::LADDER),
+    ("CLIFF", TileFlags::CLIFF),
+    ("VOID", TileFlags::VOID),
+    ("PATH", TileFlags::PATH),
+    ("LEFT", TileFlags::LEFT),
+    ("RIGHT", TileFlags::RIGHT),
+    ("UP", TileFlags::UP),
+    ("DOWN", TileFlags::DOWN),
+];
+
 impl TileFlags {
     pub fn from_raw(props: &raw::Properties) -> Fallible<TileFlags> {
         let mut flags = TileFlags::NONE;
         if let Some(raw::Property::String(prop)) = props.properties.get("flags") {
             for flag in prop.split('|') {
-                flags |= match flag.trim() {
-                    "NULL" => TileFlags::NULL,
-                    "WALL" => TileFlags::WALL,
-                    "LADDER" => TileFlags::LADDER,
-                    "CLIFF" => TileFlags::CLIFF,
-                    "VOID" => TileFlags::VOID,
-                    "PATH" => TileFlags::PATH,
-                    "LEFT" => TileFlags::LEFT,
-                    "RIGHT" => TileFlags::RIGHT,
-                    "UP" => TileFlags::UP,
-                    "DOWN" => TileFlags::DOWN,
-
-                    "" | "NONE" => TileFlags::NONE,
-                    _ => return Err(err_msg(format!("Unknown tile flag `{}`", flag))),
-                };
+                let flag = flag.trim();
+                if flag.is_empty() || flag == "NONE" {
+                    continue;
+                }
+                flags |= TILE_FLAG_NAMES
+                    .iter()
+                    .find(|&&(name, _)| name == flag)
+                    .map(|&(_, value)| value)
+                    .ok_or_else(|| err_msg(format!("Unknown tile flag `{}`", flag)))?;
             }
         }
         Ok(flags)
     }
 
+    /// The `flags` property value these flags were loaded from, e.g. `WALL|LADDER`
+    pub fn to_raw(self) -> String {
+        TILE_FLAG_NAMES
+            .iter()
+            .filter(|&&(_, value)| self.is_set(value))
+            .map(|&(name, _)| name)
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
     pub fn is_sensor(self) -> bool {
         self & (TileFlags::LADDER | TileFlags::PATH) != TileFlags::NONE
     }
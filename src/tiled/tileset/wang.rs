@@ -0,0 +1,252 @@
+use crate::tiled::map::properties::Properties;
+use crate::tiled::map::LocalTileId;
+use crate::tiled::raw;
+
+use failure::{err_msg, Fallible};
+use math2d::Color;
+use rand::Rng;
+
+/// A set of tiles tagged with which terrain (color) each of their corners and/or edges belongs
+/// to, used for autotiling
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WangSet {
+    pub name: String,
+    pub kind: WangType,
+    /// Color `i` in a `WangId` refers to `colors[i - 1]`
+    pub colors: Box<[WangColor]>,
+    pub tiles: Box<[WangTile]>,
+    pub properties: Properties,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WangType {
+    /// Only the corners of a tile are matched
+    Corner,
+    /// Only the edges of a tile are matched
+    Edge,
+    Mixed,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WangColor {
+    pub name: String,
+    pub color: Color,
+    pub probability: f32,
+    pub properties: Properties,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct WangTile {
+    pub tile: LocalTileId,
+    pub wangid: WangId,
+    /// The tile's own probability, not including the probability of its colors
+    pub probability: f32,
+}
+
+/// Color index (1-based, 0 for none) of the top edge, top-right corner, right edge,
+/// bottom-right corner, bottom edge, bottom-left corner, left edge and top-left corner, in the
+/// same order Tiled uses
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WangId(pub [u8; 8]);
+
+impl WangId {
+    pub const TOP: usize = 0;
+    pub const TOP_RIGHT: usize = 1;
+    pub const RIGHT: usize = 2;
+    pub const BOTTOM_RIGHT: usize = 3;
+    pub const BOTTOM: usize = 4;
+    pub const BOTTOM_LEFT: usize = 5;
+    pub const LEFT: usize = 6;
+    pub const TOP_LEFT: usize = 7;
+
+    /// Fills the unset (0) positions of `self` from `other`
+    pub fn or(self, other: WangId) -> WangId {
+        let mut ids = self.0;
+        for (id, &fallback) in ids.iter_mut().zip(other.0.iter()) {
+            if *id == 0 {
+                *id = fallback;
+            }
+        }
+        WangId(ids)
+    }
+}
+
+impl WangType {
+    /// Whether position `i` of a `WangId` matters for this kind of set
+    pub fn uses(self, i: usize) -> bool {
+        match self {
+            WangType::Corner => i % 2 == 1,
+            WangType::Edge => i % 2 == 0,
+            WangType::Mixed => true,
+        }
+    }
+}
+
+impl WangSet {
+    pub fn from_raw(raw: &raw::tileset::wang::WangSet, tiles: &[raw::Tile]) -> Fallible<WangSet> {
+        let colors = raw
+            .colors
+            .iter()
+            .map(|color| WangColor {
+                name: color.name.clone(),
+                color: color.color,
+                probability: color.probability,
+                properties: Properties::from_raw(&color.properties),
+            })
+            .collect::<Box<[_]>>();
+
+        let tiles = raw
+            .tiles
+            .iter()
+            .map(|tile| {
+                if tile.wangid.iter().any(|&id| id as usize > colors.len()) {
+                    return Err(err_msg(format!(
+                        "Wang set '{}' references a color it doesn't have",
+                        raw.name
+                    )));
+                }
+                Ok(WangTile {
+                    tile: LocalTileId(tile.tileid.0 as u16),
+                    wangid: WangId(tile.wangid),
+                    probability: tile_probability(tiles, tile.tileid),
+                })
+            })
+            .collect::<Fallible<_>>()?;
+
+        let kind = match raw.wangtype {
+            raw::tileset::wang::WangType::Corner => WangType::Corner,
+            raw::tileset::wang::WangType::Edge => WangType::Edge,
+            raw::tileset::wang::WangType::Mixed => WangType::Mixed,
+        };
+
+        Ok(WangSet {
+            name: raw.name.clone(),
+            kind,
+            colors,
+            tiles,
+            properties: Properties::from_raw(&raw.properties),
+        })
+    }
+
+    /// Converts the terrain types of tilesets from Tiled 1.4 and earlier into a corner set, the
+    /// same way newer versions of Tiled do. Returns None if the tileset has no terrains.
+    pub fn from_terrains(
+        terrains: &[raw::tileset::terrain::Terrain],
+        tiles: &[raw::Tile],
+    ) -> Fallible<Option<WangSet>> {
+        if terrains.is_empty() {
+            return Ok(None);
+        }
+
+        let colors = terrains
+            .iter()
+            .map(|terrain| WangColor {
+                name: terrain.name.clone(),
+                color: Color::WHITE,
+                probability: 1.0,
+                properties: Properties::from_raw(&terrain.properties),
+            })
+            .collect::<Box<[_]>>();
+
+        let mut wang_tiles = Vec::new();
+        for tile in tiles.iter() {
+            let terrain = match &tile.terrain {
+                Some(terrain) => terrain,
+                None => continue,
+            };
+
+            // Top-left, top-right, bottom-left, bottom-right, with empty or -1 for none
+            let corners = terrain
+                .split(',')
+                .map(|c| match c.trim() {
+                    "" => Ok(0),
+                    c => c.parse::<i32>().map(|i| (i + 1).max(0) as usize),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+                .filter(|corners| corners.len() == 4)
+                .ok_or_else(|| err_msg(format!("Invalid tile terrain '{}'", terrain)))?;
+            if corners.iter().any(|&c| c > colors.len()) {
+                return Err(err_msg("Tile references a terrain that doesn't exist"));
+            }
+
+            let mut wangid = WangId::default();
+            wangid.0[WangId::TOP_LEFT] = corners[0] as u8;
+            wangid.0[WangId::TOP_RIGHT] = corners[1] as u8;
+            wangid.0[WangId::BOTTOM_LEFT] = corners[2] as u8;
+            wangid.0[WangId::BOTTOM_RIGHT] = corners[3] as u8;
+            wang_tiles.push(WangTile {
+                tile: LocalTileId(tile.id.0 as u16),
+                wangid,
+                probability: tile.probability.unwrap_or(1.0),
+            });
+        }
+
+        Ok(Some(WangSet {
+            name: "Terrains".into(),
+            kind: WangType::Corner,
+            colors,
+            tiles: wang_tiles.into_boxed_slice(),
+            properties: Properties::default(),
+        }))
+    }
+
+    /// The colors of `tile`, if it's part of this set
+    pub fn wangid(&self, tile: LocalTileId) -> Option<WangId> {
+        self.tiles.iter().find(|t| t.tile == tile).map(|t| t.wangid)
+    }
+
+    /// Picks a tile matching `wanted`, where 0 matches any color. When several tiles match one is
+    /// chosen at random, weighted by the tile's probability times the probability of each of its
+    /// colors, like Tiled's terrain brush. If nothing matches exactly the tiles with the fewest
+    /// mismatched corners/edges are used instead. Returns None if the set has no usable tiles.
+    pub fn choose(&self, wanted: WangId, rng: &mut impl Rng) -> Option<LocalTileId> {
+        let mismatches = |tile: &WangTile| {
+            (0..8)
+                .filter(|&i| self.kind.uses(i) && wanted.0[i] != 0)
+                .filter(|&i| tile.wangid.0[i] != wanted.0[i])
+                .count()
+        };
+
+        let best = self.tiles.iter().map(mismatches).min()?;
+        let candidates = self
+            .tiles
+            .iter()
+            .filter(|tile| mismatches(tile) == best)
+            .map(|tile| (tile.tile, self.probability(tile)))
+            .collect::<Vec<_>>();
+
+        let total: f32 = candidates.iter().map(|&(_, p)| p).sum();
+        if total <= 0.0 {
+            return candidates.first().map(|&(tile, _)| tile);
+        }
+
+        let mut pick = rng.gen::<f32>() * total;
+        for &(tile, probability) in candidates.iter() {
+            if pick < probability {
+                return Some(tile);
+            }
+            pick -= probability;
+        }
+        candidates.last().map(|&(tile, _)| tile)
+    }
+
+    fn probability(&self, tile: &WangTile) -> f32 {
+        (0..8)
+            .filter(|&i| self.kind.uses(i))
+            .filter_map(|i| match tile.wangid.0[i] {
+                0 => None,
+                color => self.colors.get(color as usize - 1),
+            })
+            .fold(tile.probability, |p, color| p * color.probability)
+    }
+}
+
+fn tile_probability(tiles: &[raw::Tile], id: raw::LocalTileId) -> f32 {
+    tiles
+        .iter()
+        .find(|tile| tile.id == id)
+        .and_then(|tile| tile.probability)
+        .unwrap_or(1.0)
+}
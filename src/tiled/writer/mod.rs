@@ -0,0 +1,429 @@
+//! Writes a `map::Map` back out as TMX so it can be opened in Tiled. Tilesets are written to
+//! TSX files next to the map, and images are written out as PNGs.
+
+use crate::tiled::map::layer::{ImageLayer, Layer, ObjectLayer, TileLayer};
+use crate::tiled::map::properties::{Properties, Property};
+use crate::tiled::map::tiledata::CHUNK_SIZE;
+use crate::tiled::map::tilesets::Tilesets;
+use crate::tiled::map::{layer::object::Shape, Map, Orientation, RenderOrder};
+use crate::tiled::map::{StaggerAxis, StaggerIndex};
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use failure::{err_msg, Fallible, ResultExt};
+use math2d::{Color, Point2f, Vector2f};
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+
+pub mod tileset;
+
+/// Saves `map` to `path`. Tilesets and images are written alongside it as
+/// `<map name>.<n>.tsx` and `<map name>.<n>.png`.
+pub fn save_tmx(path: &Path, map: &Map) -> Fallible<()> {
+    let files = SidecarFiles::new(path)?;
+
+    let mut tileset_sources = Vec::new();
+    for (i, (_, tileset)) in map.tilesets.iter().enumerate() {
+        let name = format!("tileset{}", i + 1);
+        let source = tileset::save_tsx(&files, &name, tileset, map.tile_size)
+            .with_context(|_| format!("Writing {}", name))?;
+        tileset_sources.push(source);
+    }
+
+    let file = std::fs::File::create(path)?;
+    let mut w = Writer::new(std::io::BufWriter::new(file));
+
+    let (width, height) = map_size(map);
+    let orientation = match map.orientation {
+        Orientation::Orthogonal => "orthogonal",
+        Orientation::Isometric => "isometric",
+        Orientation::Staggered { .. } => "staggered",
+    };
+    let renderorder = match map.render_order {
+        RenderOrder::RightDown => "right-down",
+        RenderOrder::RightUp => "right-up",
+        RenderOrder::LeftDown => "left-down",
+        RenderOrder::LeftUp => "left-up",
+    };
+    let mut attrs = vec![
+        ("version", "1.2".into()),
+        ("tiledversion", "1.2.3".into()),
+        ("orientation", orientation.into()),
+        ("renderorder", renderorder.into()),
+        ("width", width.max(map.width).to_string()),
+        ("height", height.max(map.height).to_string()),
+        ("tilewidth", (map.tile_size.x as i32).to_string()),
+        ("tileheight", (map.tile_size.y as i32).to_string()),
+        // Tile data is always written in chunks
+        ("infinite", "1".into()),
+    ];
+    if let Orientation::Staggered { axis, index } = map.orientation {
+        let axis = match axis {
+            StaggerAxis::X => "x",
+            StaggerAxis::Y => "y",
+        };
+        let index = match index {
+            StaggerIndex::Odd => "odd",
+            StaggerIndex::Even => "even",
+        };
+        attrs.push(("staggeraxis", axis.into()));
+        attrs.push(("staggerindex", index.into()));
+    }
+    if let Some(color) = map.background_color {
+        attrs.push(("backgroundcolor", color_to_raw(color)));
+    }
+    w.start("map", &attrs)?;
+    w.properties(&map.properties)?;
+
+    for ((firstgid, _), source) in map.tilesets.iter().zip(tileset_sources.iter()) {
+        w.empty(
+            "tileset",
+            &[
+                ("firstgid", firstgid.0.to_string()),
+                ("source", source.clone()),
+            ],
+        )?;
+    }
+
+    for (i, layer) in map.layers.iter().enumerate() {
+        let id = (i + 1).to_string();
+        match layer {
+            Layer::Tile(layer) => {
+                write_tile_layer(&mut w, &id, layer, &map.tilesets, map.tile_size)?
+            }
+            Layer::Object(layer) => {
+                write_object_layer(&mut w, &id, layer, &map.tilesets, map.tile_size)?
+            }
+            Layer::Image(layer) => {
+                let source = files.write_image(&format!("image{}", id), &layer.image.to_rgba()?)?;
+                write_image_layer(&mut w, &id, layer, &source, map.tile_size)?;
+            }
+            Layer::Unused => (),
+        }
+    }
+
+    w.end()?;
+    Ok(())
+}
+
+/// Names the files written next to the map
+pub struct SidecarFiles {
+    dir: PathBuf,
+    stem: String,
+}
+
+impl SidecarFiles {
+    fn new(map_path: &Path) -> Fallible<SidecarFiles> {
+        let dir = map_path.parent().unwrap_or(Path::new(".")).to_owned();
+        let stem = map_path
+            .file_stem()
+            .ok_or_else(|| err_msg("Map path has no file name"))?
+            .to_string_lossy()
+            .into_owned();
+        Ok(SidecarFiles { dir, stem })
+    }
+
+    /// Returns the path of the sidecar file relative to the map
+    pub fn name(&self, name: &str, ext: &str) -> String {
+        format!("{}.{}.{}", self.stem, name, ext)
+    }
+
+    pub fn create(&self, name: &str, ext: &str) -> Fallible<(String, std::fs::File)> {
+        let name = self.name(name, ext);
+        let file = std::fs::File::create(self.dir.join(&name))?;
+        Ok((name, file))
+    }
+
+    pub fn write_image(&self, name: &str, image: &image::RgbaImage) -> Fallible<String> {
+        let name = self.name(name, "png");
+        image
+            .save(self.dir.join(&name))
+            .with_context(|_| format!("Writing {}", name))?;
+        Ok(name)
+    }
+}
+
+/// Thin wrapper around the xml-rs writer that takes owned attribute values
+pub struct Writer<W: Write> {
+    xml: EventWriter<W>,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(inner: W) -> Self {
+        let xml = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(inner);
+        Writer { xml }
+    }
+
+    pub fn start(&mut self, tag: &str, attrs: &[(&str, String)]) -> Fallible<()> {
+        let mut elem = XmlEvent::start_element(tag);
+        for (name, value) in attrs {
+            elem = elem.attr(*name, value);
+        }
+        self.xml.write(elem)?;
+        Ok(())
+    }
+
+    pub fn end(&mut self) -> Fallible<()> {
+        self.xml.write(XmlEvent::end_element())?;
+        Ok(())
+    }
+
+    pub fn empty(&mut self, tag: &str, attrs: &[(&str, String)]) -> Fallible<()> {
+        self.start(tag, attrs)?;
+        self.end()
+    }
+
+    pub fn text(&mut self, text: &str) -> Fallible<()> {
+        self.xml.write(XmlEvent::characters(text))?;
+        Ok(())
+    }
+
+    pub fn properties(&mut self, props: &Properties) -> Fallible<()> {
+        if props.is_empty() {
+            return Ok(());
+        }
+
+        // Sorted to keep the output stable between saves
+        let mut sorted: Vec<_> = props.properties.iter().collect();
+        sorted.sort_by_key(|&(name, _)| name);
+
+        self.start("properties", &[])?;
+        for (name, prop) in sorted {
+            let (kind, value) = match prop {
+                Property::String(value) => ("string", value.clone()),
+                Property::Int(value) => ("int", value.to_string()),
+                Property::Float(value) => ("float", value.to_string()),
+                Property::Bool(value) => ("bool", value.to_string()),
+                Property::Color(c) => ("color", color_to_raw(*c)),
+                Property::File(value) => ("file", value.clone()),
+                Property::Object(value) => ("object", value.to_string()),
+                Property::Class {
+                    propertytype,
+                    properties,
+                } => {
+                    self.start(
+                        "property",
+                        &[
+                            ("name", name.clone()),
+                            ("type", "class".into()),
+                            ("propertytype", propertytype.clone()),
+                        ],
+                    )?;
+                    self.properties(properties)?;
+                    self.end()?;
+                    continue;
+                }
+            };
+
+            self.empty(
+                "property",
+                &[
+                    ("name", name.clone()),
+                    ("type", kind.into()),
+                    ("value", value),
+                ],
+            )?;
+        }
+        self.end()
+    }
+}
+
+/// Formats a color as `#AARRGGBB` like Tiled does
+fn color_to_raw(c: Color) -> String {
+    let byte = |f: f32| (f * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}{:02x}",
+        byte(c.a),
+        byte(c.r),
+        byte(c.g),
+        byte(c.b)
+    )
+}
+
+/// The inverse of `layer::pixel_to_world`
+pub fn world_to_pixel(world: Point2f, tile_size: Vector2f) -> Point2f {
+    let x = (world.x + 0.5) * tile_size.x;
+    let y = -(world.y - 0.5) * tile_size.y;
+    (x, y).into()
+}
+
+/// `props` with the `flags` property replaced by `flags`, so edits to the flags in the engine
+/// are what gets saved
+pub fn with_flags(props: &Properties, flags: String) -> Properties {
+    let mut props = props.clone();
+    if flags.is_empty() {
+        props.properties.remove("flags");
+    } else {
+        props
+            .properties
+            .insert("flags".into(), Property::String(flags));
+    }
+    props
+}
+
+fn common_attrs(id: &str, name: &str, opacity: f32, visible: bool) -> Vec<(&'static str, String)> {
+    vec![
+        ("id", id.to_string()),
+        ("name", name.to_string()),
+        ("opacity", opacity.to_string()),
+        ("visible", (visible as i32).to_string()),
+    ]
+}
+
+/// Size of the map in tiles, covering every chunk of every tile layer
+fn map_size(map: &Map) -> (i32, i32) {
+    let mut max = (0, 0);
+    for layer in map.layers.iter() {
+        if let Layer::Tile(layer) = layer {
+            for pos in layer.data.chunks.keys() {
+                max.0 = max.0.max((pos.x + 1) * CHUNK_SIZE);
+                max.1 = max.1.max((pos.y + 1) * CHUNK_SIZE);
+            }
+        }
+    }
+    max
+}
+
+fn write_tile_layer(
+    w: &mut Writer<impl Write>,
+    id: &str,
+    layer: &TileLayer,
+    sets: &Tilesets,
+    tile_size: Vector2f,
+) -> Fallible<()> {
+    use byteorder::{WriteBytesExt, LE};
+    use flate2::write::ZlibEncoder;
+
+    let (width, height) = (CHUNK_SIZE.to_string(), CHUNK_SIZE.to_string());
+    let offset = layer.offset * [1.0, -1.0] * tile_size;
+    let mut attrs = common_attrs(id, &layer.name, layer.opacity, layer.visible);
+    attrs.push(("width", width.clone()));
+    attrs.push(("height", height.clone()));
+    attrs.push(("offsetx", offset.x.to_string()));
+    attrs.push(("offsety", offset.y.to_string()));
+    w.start("layer", &attrs)?;
+    w.properties(&with_flags(&layer.properties, layer.flags.to_raw()))?;
+
+    w.start(
+        "data",
+        &[
+            ("encoding", "base64".into()),
+            ("compression", "zlib".into()),
+        ],
+    )?;
+
+    // Sorted so saving the same map twice gives the same file
+    let mut chunks: Vec<_> = layer.data.chunks.iter().collect();
+    chunks.sort_by_key(|(pos, _)| (pos.y, pos.x));
+    for (pos, chunk) in chunks {
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+        for &tile in chunk.data.iter() {
+            encoder.write_u32::<LE>(sets.tile_to_raw(tile).0)?;
+        }
+        let content = base64::encode(&encoder.finish()?);
+
+        w.start(
+            "chunk",
+            &[
+                ("x", (pos.x * CHUNK_SIZE).to_string()),
+                ("y", (pos.y * CHUNK_SIZE).to_string()),
+                ("width", width.clone()),
+                ("height", height.clone()),
+            ],
+        )?;
+        w.text(&content)?;
+        w.end()?;
+    }
+
+    w.end()?;
+    w.end()
+}
+
+fn write_object_layer(
+    w: &mut Writer<impl Write>,
+    id: &str,
+    layer: &ObjectLayer,
+    sets: &Tilesets,
+    tile_size: Vector2f,
+) -> Fallible<()> {
+    use std::f32::consts::PI;
+
+    w.start(
+        "objectgroup",
+        &common_attrs(id, &layer.name, layer.opacity, layer.visible),
+    )?;
+    w.properties(&layer.properties)?;
+
+    for object in layer.objects.iter() {
+        let pos = world_to_pixel(object.position, tile_size);
+        let size = object.size * tile_size;
+        let mut attrs = vec![
+            ("id", object.id.to_string()),
+            ("name", object.name.clone()),
+            ("type", object.kind.clone()),
+            ("x", pos.x.to_string()),
+            ("y", pos.y.to_string()),
+            ("width", size.x.to_string()),
+            ("height", size.y.to_string()),
+            ("rotation", (object.rotation * 180.0 / PI).to_string()),
+            ("visible", (object.visible as i32).to_string()),
+        ];
+        if let Some(tile) = object.tile {
+            attrs.push(("gid", sets.tile_to_raw(tile).0.to_string()));
+        }
+
+        w.start("object", &attrs)?;
+        w.properties(&object.properties)?;
+        let points = |points: &[Vector2f]| {
+            points
+                .iter()
+                .map(|&p| {
+                    let p = p * tile_size;
+                    format!("{},{}", p.x, -p.y)
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match &object.shape {
+            Shape::Rectangle => (),
+            Shape::Ellipse => w.empty("ellipse", &[])?,
+            Shape::Point => w.empty("point", &[])?,
+            Shape::Polygon(p) => w.empty("polygon", &[("points", points(p))])?,
+            Shape::Polyline(p) => w.empty("polyline", &[("points", points(p))])?,
+        }
+        w.end()?;
+    }
+
+    w.end()
+}
+
+fn write_image_layer(
+    w: &mut Writer<impl Write>,
+    id: &str,
+    layer: &ImageLayer,
+    source: &str,
+    tile_size: Vector2f,
+) -> Fallible<()> {
+    let offset = world_to_pixel(layer.position, tile_size);
+    let mut attrs = common_attrs(id, &layer.name, layer.opacity, layer.visible);
+    attrs.push(("offsetx", offset.x.to_string()));
+    attrs.push(("offsety", offset.y.to_string()));
+    attrs.push(("parallaxx", layer.parallax.x.to_string()));
+    attrs.push(("parallaxy", layer.parallax.y.to_string()));
+    attrs.push(("repeatx", (layer.repeat_x as i32).to_string()));
+    attrs.push(("repeaty", (layer.repeat_y as i32).to_string()));
+
+    w.start("imagelayer", &attrs)?;
+    w.properties(&layer.properties)?;
+    w.empty(
+        "image",
+        &[
+            ("source", source.to_string()),
+            ("width", layer.image.width.to_string()),
+            ("height", layer.image.height.to_string()),
+        ],
+    )?;
+    w.end()
+}
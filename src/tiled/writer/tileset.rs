@@ -0,0 +1,254 @@
+use super::{color_to_raw, with_flags, SidecarFiles, Writer};
+use crate::tiled::tileset::tile::{Collider, Shape, Tile};
+use crate::tiled::tileset::wang::WangType;
+use crate::tiled::tileset::Tileset;
+
+use std::io::Write;
+
+use failure::Fallible;
+use math2d::{Point2f, Vector2f};
+
+/// Writes `tileset` to a TSX file and its image(s) to PNGs. Returns the TSX file name relative
+/// to the map.
+pub fn save_tsx(
+    files: &SidecarFiles,
+    name: &str,
+    tileset: &Tileset,
+    tile_size: Vector2f,
+) -> Fallible<String> {
+    let (source, file) = files.create(name, "tsx")?;
+    let mut w = Writer::new(std::io::BufWriter::new(file));
+    let image = tileset.image.to_rgba()?;
+
+    let mut attrs = vec![
+        ("name", name.to_string()),
+        ("tilewidth", tileset.tile_width.to_string()),
+        ("tileheight", tileset.tile_height.to_string()),
+        ("tilecount", tileset.tiles.len().to_string()),
+        ("columns", tileset.columns.to_string()),
+    ];
+    if tileset.columns != 0 {
+        attrs.push(("spacing", tileset.spacing.to_string()));
+        attrs.push(("margin", tileset.margin.to_string()));
+    }
+    w.start("tileset", &attrs)?;
+
+    if tileset.offset != Vector2f::new(0.0, 0.0) {
+        let offset = tileset.offset * tile_size;
+        w.empty(
+            "tileoffset",
+            &[("x", offset.x.to_string()), ("y", (-offset.y).to_string())],
+        )?;
+    }
+
+    // Image collections get split back up into one image per tile
+    let is_collection = tileset.columns == 0;
+    if !is_collection {
+        let image_source = files.write_image(name, &image)?;
+        w.empty(
+            "image",
+            &[
+                ("source", image_source),
+                ("width", tileset.image.width.to_string()),
+                ("height", tileset.image.height.to_string()),
+            ],
+        )?;
+    }
+
+    for (id, tile) in tileset.tiles.iter().enumerate() {
+        let rect = tileset.image_rects[id];
+        let (width, height) = (
+            (rect.right - rect.left) as u32,
+            (rect.bottom - rect.top) as u32,
+        );
+        let has_image = is_collection && width > 0 && height > 0;
+        let properties = with_flags(&tile.properties, tile.flags.to_raw());
+        // Only kept on the Wang tiles at runtime
+        let probability = tileset
+            .wang_sets
+            .iter()
+            .flat_map(|set| set.tiles.iter())
+            .find(|t| t.tile.0 as usize == id)
+            .map(|t| t.probability)
+            .filter(|&p| p != 1.0);
+        if !has_image
+            && probability.is_none()
+            && properties.is_empty()
+            && tile.colliders.is_empty()
+            && tile.animation.is_empty()
+        {
+            continue;
+        }
+
+        let mut attrs = vec![("id", id.to_string())];
+        if let Some(probability) = probability {
+            attrs.push(("probability", probability.to_string()));
+        }
+        w.start("tile", &attrs)?;
+        w.properties(&properties)?;
+
+        if has_image {
+            let (x0, y0) = (rect.left as u32, rect.top as u32);
+            let tile_image =
+                image::RgbaImage::from_fn(width, height, |x, y| *image.get_pixel(x0 + x, y0 + y));
+            let tile_source = files.write_image(&format!("{}.{}", name, id), &tile_image)?;
+            w.empty(
+                "image",
+                &[
+                    ("source", tile_source),
+                    ("width", width.to_string()),
+                    ("height", height.to_string()),
+                ],
+            )?;
+        }
+
+        write_colliders(&mut w, tile, tile_size)?;
+
+        if !tile.animation.is_empty() {
+            w.start("animation", &[])?;
+            for frame in tile.animation.iter() {
+                w.empty(
+                    "frame",
+                    &[
+                        ("tileid", frame.tile.0.to_string()),
+                        ("duration", frame.duration.to_string()),
+                    ],
+                )?;
+            }
+            w.end()?;
+        }
+
+        w.end()?;
+    }
+
+    write_wang_sets(&mut w, tileset)?;
+
+    w.end()?;
+    Ok(source)
+}
+
+/// Writes the Wang sets in the format used since Tiled 1.5. Sets converted from old terrain
+/// types come back out as Wang sets too.
+fn write_wang_sets(w: &mut Writer<impl Write>, tileset: &Tileset) -> Fallible<()> {
+    if tileset.wang_sets.is_empty() {
+        return Ok(());
+    }
+
+    w.start("wangsets", &[])?;
+    for set in tileset.wang_sets.iter() {
+        let kind = match set.kind {
+            WangType::Corner => "corner",
+            WangType::Edge => "edge",
+            WangType::Mixed => "mixed",
+        };
+        w.start(
+            "wangset",
+            &[
+                ("name", set.name.clone()),
+                ("type", kind.into()),
+                ("tile", "-1".into()),
+            ],
+        )?;
+        w.properties(&set.properties)?;
+
+        for color in set.colors.iter() {
+            w.start(
+                "wangcolor",
+                &[
+                    ("name", color.name.clone()),
+                    ("color", color_to_raw(color.color)),
+                    ("tile", "-1".into()),
+                    ("probability", color.probability.to_string()),
+                ],
+            )?;
+            w.properties(&color.properties)?;
+            w.end()?;
+        }
+
+        for tile in set.tiles.iter() {
+            let wangid = tile
+                .wangid
+                .0
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            w.empty(
+                "wangtile",
+                &[("tileid", tile.tile.0.to_string()), ("wangid", wangid)],
+            )?;
+        }
+        w.end()?;
+    }
+    w.end()
+}
+
+/// The inverse of `Collider::from_raw`. Polygons were triangulated on load, so each triangle
+/// comes back out as its own polygon.
+fn write_colliders(w: &mut Writer<impl Write>, tile: &Tile, tile_size: Vector2f) -> Fallible<()> {
+    use std::f32::consts::PI;
+
+    if tile.colliders.is_empty() {
+        return Ok(());
+    }
+
+    let to_pixel = |p: Point2f| Vector2f::new(p.x, -p.y) * tile_size;
+
+    w.start("objectgroup", &[("draworder", "index".into())])?;
+    for (i, collider) in tile.colliders.iter().enumerate() {
+        let Collider {
+            shape,
+            rotation,
+            origin,
+            flags,
+        } = collider;
+
+        let origin_px = to_pixel(*origin);
+        let mut attrs = vec![
+            ("id", (i + 1).to_string()),
+            ("x", origin_px.x.to_string()),
+            ("y", origin_px.y.to_string()),
+            ("rotation", (rotation * 180.0 / PI).to_string()),
+        ];
+        let points = |points: &[Point2f]| {
+            points
+                .iter()
+                .map(|&p| {
+                    let p = to_pixel(p) - origin_px;
+                    format!("{},{}", p.x, p.y)
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let (tag, tag_attrs) = match shape {
+            Shape::Rectangle(rect) => {
+                attrs.push((
+                    "width",
+                    ((rect.right - rect.left).abs() * tile_size.x).to_string(),
+                ));
+                attrs.push((
+                    "height",
+                    ((rect.top - rect.bottom).abs() * tile_size.y).to_string(),
+                ));
+                (None, vec![])
+            }
+            Shape::Point(_) => (Some("point"), vec![]),
+            Shape::Ellipse(ellipse) => {
+                attrs.push(("width", (ellipse.radius_x * 2.0 * tile_size.x).to_string()));
+                attrs.push(("height", (ellipse.radius_y * 2.0 * tile_size.y).to_string()));
+                (Some("ellipse"), vec![])
+            }
+            Shape::Triangle(tri) => (Some("polygon"), vec![("points", points(&tri[..]))]),
+            Shape::Chain(chain) => (Some("polyline"), vec![("points", points(chain))]),
+        };
+
+        w.start("object", &attrs)?;
+        w.properties(&with_flags(&Default::default(), flags.to_raw()))?;
+        if let Some(tag) = tag {
+            w.empty(tag, &tag_attrs)?;
+        }
+        w.end()?;
+    }
+    w.end()
+}
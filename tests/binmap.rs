@@ -0,0 +1,92 @@
+//! Prebaked maps have to keep loading across engine updates: headerless version 0 files get
+//! upgraded, while files from a newer engine or with a damaged payload are rejected.
+
+use pixel_arena_game::tiled;
+use pixel_arena_game::tiled::binmap::{self, FORMAT_VERSION};
+use pixel_arena_game::tiled::map::layer::Layer;
+use pixel_arena_game::tiled::map::{LocalTileId, Map, TileId, TilesetId};
+
+use std::path::Path;
+
+use math2d::Point2i;
+
+/// A headerless map in the layout binmaps had before versioning: one 2x2 tileset of 16px
+/// tiles on a solid 32x32 WebP, and one tile layer with a single chunk at the origin.
+fn load_v0() -> Map {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/binmap/v0.binmap");
+    let file = std::fs::File::open(&path).unwrap();
+    tiled::load_binmap(file).unwrap_or_else(|err| panic!("Loading {}: {}", path.display(), err))
+}
+
+/// The current binmap of the v0 fixture, and where its content hash starts
+fn current_binmap() -> (Vec<u8>, usize) {
+    let mut data = Vec::new();
+    tiled::save_binmap(&mut data, &load_v0()).unwrap();
+    let header = binmap::read_header(&data[..]).unwrap().unwrap();
+    (data, 8 + 4 + 2 + header.engine_version.len())
+}
+
+fn tile(id: u16) -> TileId {
+    TileId::new(TilesetId(1), LocalTileId(id))
+}
+
+#[test]
+fn v0_is_upgraded() {
+    let map = load_v0();
+
+    let tileset = map.tilesets.get(TilesetId(1)).unwrap();
+    assert_eq!((tileset.columns, tileset.rows), (2, 2));
+    assert_eq!((tileset.image.width, tileset.image.height), (32, 32));
+    assert_eq!(tileset.tiles.len(), 4);
+    assert!(map.infinite);
+
+    assert_eq!(map.layers.len(), 1);
+    let layer = match &map.layers[0] {
+        Layer::Tile(layer) => layer,
+        _ => panic!("expected a tile layer"),
+    };
+    assert_eq!(layer.name, "Layer 1");
+    assert_eq!(layer.data.chunks.len(), 1);
+
+    let chunk = &layer.data.chunks[&Point2i::new(0, 0)];
+    assert_eq!(&chunk.data[..4], &[tile(0), tile(1), tile(2), tile(3)]);
+    assert_eq!(chunk.data[17], tile(3));
+    let empty = chunk
+        .data
+        .iter()
+        .filter(|&&t| t == TileId::default())
+        .count();
+    assert_eq!(empty, chunk.data.len() - 5);
+}
+
+#[test]
+fn current_version_round_trips() {
+    let (data, _) = current_binmap();
+    let header = binmap::read_header(&data[..]).unwrap().unwrap();
+    assert_eq!(header.format_version, FORMAT_VERSION);
+
+    let map = tiled::load_binmap(&data[..]).unwrap();
+    assert_eq!(map.layers.len(), 1);
+}
+
+#[test]
+fn newer_version_is_not_supported() {
+    let (mut data, _) = current_binmap();
+    data[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+
+    let err = tiled::load_binmap(&data[..])
+        .err()
+        .expect("newer version should fail");
+    assert!(err.to_string().contains("is not supported"), "{}", err);
+}
+
+#[test]
+fn wrong_hash_is_corrupt() {
+    let (mut data, hash) = current_binmap();
+    data[hash] ^= 0xff;
+
+    let err = tiled::load_binmap(&data[..])
+        .err()
+        .expect("wrong hash should fail");
+    assert!(err.to_string().contains("corrupt"), "{}", err);
+}
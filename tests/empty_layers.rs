@@ -0,0 +1,89 @@
+//! Infinite maps write tile layers without any chunks as empty data. Those should load as empty
+//! layers, while a finite layer that's missing its tiles is still an error.
+
+use pixel_arena_game::tiled;
+use pixel_arena_game::tiled::map::layer::Layer;
+use pixel_arena_game::tiled::map::Map;
+use pixel_arena_game::tiled::source::Source;
+
+use failure::Fallible;
+
+const INFINITE_TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.2" tiledversion="1.2.2" orientation="orthogonal" renderorder="right-down" width="16" height="16" tilewidth="16" tileheight="16" infinite="1">
+ <layer id="1" name="Empty" width="16" height="16">
+  <data encoding="base64" compression="zlib"/>
+ </layer>
+</map>
+"#;
+
+const FINITE_TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.2" tiledversion="1.2.2" orientation="orthogonal" renderorder="right-down" width="4" height="4" tilewidth="16" tileheight="16" infinite="0">
+ <layer id="1" name="Empty" width="4" height="4">
+  <data encoding="csv"/>
+ </layer>
+</map>
+"#;
+
+const INFINITE_TMJ: &str = r#"{
+    "version": "1.2",
+    "orientation": "orthogonal",
+    "width": 16,
+    "height": 16,
+    "tilewidth": 16,
+    "tileheight": 16,
+    "infinite": true,
+    "layers": [{
+        "id": 1,
+        "name": "Empty",
+        "type": "tilelayer",
+        "width": 16,
+        "height": 16,
+        "encoding": "base64",
+        "compression": "zlib",
+        "chunks": []
+    }]
+}
+"#;
+
+fn load(name: &str, contents: &str) -> Fallible<Map> {
+    let dir = std::env::temp_dir().join(format!(
+        "pixel-arena-game-test-empty-layers-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    std::fs::write(&path, contents)?;
+
+    let source = Source::new_file(&path);
+    let map = match source.extension() {
+        Some("tmj") => tiled::load_tmj(source),
+        _ => tiled::load_tmx(source),
+    };
+    let _ = std::fs::remove_file(&path);
+    Ok(map?.data)
+}
+
+fn assert_one_empty_layer(map: &Map) {
+    assert_eq!(map.layers.len(), 1);
+    match &map.layers[0] {
+        Layer::Tile(layer) => assert!(layer.data.chunks.is_empty()),
+        _ => panic!("expected a tile layer"),
+    }
+}
+
+#[test]
+fn infinite_tmx_without_chunks() {
+    let map = load("infinite.tmx", INFINITE_TMX).unwrap();
+    assert_one_empty_layer(&map);
+}
+
+#[test]
+fn infinite_tmj_without_chunks() {
+    let map = load("infinite.tmj", INFINITE_TMJ).unwrap();
+    assert_one_empty_layer(&map);
+}
+
+#[test]
+fn finite_tmx_without_tiles_is_an_error() {
+    assert!(load("finite.tmx", FINITE_TMX).is_err());
+}
@@ -0,0 +1,66 @@
+//! The same map saved with every tile layer encoding Tiled supports should load into the same
+//! tiles, both as an infinite map with chunked data and as a finite map with plain data.
+
+use pixel_arena_game::tiled;
+use pixel_arena_game::tiled::map::layer::Layer;
+use pixel_arena_game::tiled::map::TileId;
+use pixel_arena_game::tiled::source::Source;
+
+use std::path::Path;
+
+const ENCODINGS: &[&str] = &["csv", "base64", "gzip", "zlib", "zstd"];
+const PLAIN_ENCODINGS: &[&str] = &["plain-csv", "plain-gzip", "plain-zlib", "plain-zstd"];
+
+/// Every chunk of every tile layer, by layer name and chunk position
+fn load_tiles(fixture: &str) -> Vec<(String, (i32, i32), Vec<TileId>)> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/encodings")
+        .join(format!("{}.tmx", fixture));
+    let map = tiled::load_tmx(Source::new_file(&path))
+        .unwrap_or_else(|err| panic!("Loading {}: {}", path.display(), err))
+        .data;
+
+    let mut tiles = Vec::new();
+    for layer in map.layers.iter() {
+        if let Layer::Tile(layer) = layer {
+            for (pos, chunk) in layer.data.chunks.iter() {
+                tiles.push((layer.name.clone(), (pos.x, pos.y), chunk.data.to_vec()));
+            }
+        }
+    }
+    tiles.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+    tiles
+}
+
+#[test]
+fn encodings_decode_identically() {
+    tiled::tileset::cache::set_dir(None);
+
+    let expected = load_tiles("csv");
+    assert_eq!(expected.len(), 4, "csv fixture should have 4 chunks");
+    assert!(expected
+        .iter()
+        .any(|(_, _, data)| data.iter().any(|&tile| tile != TileId::default())));
+
+    for &encoding in ENCODINGS[1..].iter() {
+        let tiles = load_tiles(encoding);
+        assert!(tiles == expected, "{} tiles differ from csv", encoding);
+    }
+}
+
+#[test]
+fn plain_encodings_decode_identically() {
+    tiled::tileset::cache::set_dir(None);
+
+    // The finite fixtures hold the same tiles as the infinite ones, moved one chunk right and
+    // down so none of them are at negative positions
+    let expected = load_tiles("csv")
+        .into_iter()
+        .map(|(name, (x, y), data)| (name, (x + 1, y + 1), data))
+        .collect::<Vec<_>>();
+
+    for &fixture in PLAIN_ENCODINGS.iter() {
+        let tiles = load_tiles(fixture);
+        assert!(tiles == expected, "{} tiles differ from csv", fixture);
+    }
+}
@@ -0,0 +1,73 @@
+//! Saving a map as TMX and loading it back should give the same tiles.
+
+use pixel_arena_game::tiled;
+use pixel_arena_game::tiled::map::layer::{Layer, LayerFlags, TileLayer};
+use pixel_arena_game::tiled::map::tiledata::TileData;
+use pixel_arena_game::tiled::map::{Map, TileId};
+use pixel_arena_game::tiled::source::Source;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Every tile layer by name, with its chunks sorted by position
+fn tile_layers(map: &Map) -> Vec<(String, Vec<((i32, i32), Vec<TileId>)>)> {
+    let mut layers = Vec::new();
+    for layer in map.layers.iter() {
+        if let Layer::Tile(layer) = layer {
+            let mut chunks = layer
+                .data
+                .chunks
+                .iter()
+                .map(|(pos, chunk)| ((pos.x, pos.y), chunk.data.to_vec()))
+                .collect::<Vec<_>>();
+            chunks.sort_by_key(|&(pos, _)| pos);
+            layers.push((layer.name.clone(), chunks));
+        }
+    }
+    layers
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "pixel-arena-game-test-{}-{}",
+        name,
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn save_and_reload_keeps_tiles() {
+    tiled::tileset::cache::set_dir(None);
+
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/encodings/zlib.tmx");
+    let mut map = tiled::load_tmx(Source::new_file(&fixture)).unwrap().data;
+    map.layers.push(Layer::Tile(TileLayer {
+        name: "Empty".into(),
+        flags: LayerFlags::NONE,
+        offset: [0.0, 0.0].into(),
+        opacity: 1.0,
+        visible: true,
+        properties: Default::default(),
+        data: TileData {
+            chunks: HashMap::new(),
+        },
+    }));
+    let expected = tile_layers(&map);
+    assert_eq!(expected.len(), 2);
+    assert!(expected[1].1.is_empty());
+
+    let dir = temp_dir("roundtrip");
+    let path = dir.join("map.tmx");
+    tiled::save_tmx(&path, &map).unwrap();
+    let reloaded = tiled::load_tmx(Source::new_file(&path))
+        .unwrap_or_else(|err| panic!("Reloading {}: {}", path.display(), err))
+        .data;
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(
+        tile_layers(&reloaded) == expected,
+        "tiles changed when saved"
+    );
+}